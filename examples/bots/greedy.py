@@ -0,0 +1,41 @@
+#!/usr/bin/env python3
+"""A greedy Speed bot for the stdio protocol: plays a card when it can, draws
+when it can't, and otherwise votes to flip.
+
+Run it with `cargo run --bin bot_host -- hard "python3 examples/bots/greedy.py"`.
+"""
+
+import json
+import sys
+
+RANKS = ["Ace", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight",
+         "Nine", "Ten", "Jack", "Queen", "King"]
+
+
+def adjacent(card, other, rules):
+    # Jokers are wild: they go on anything and take anything.
+    if "Joker" in (card["rank"], other["rank"]):
+        return True
+    gap = abs(RANKS.index(card["rank"]) - RANKS.index(other["rank"]))
+    return gap == 1 or (gap == 12 and rules["wraparound"])
+
+
+def decide(message):
+    view = message["player_view"]
+    for hand_index, card in enumerate(view["player_hand"]):
+        for side_index, side in enumerate(["LEFT", "RIGHT"]):
+            active = view["active_cards"][side_index]
+            if card and active and adjacent(card, active, view["rules"]):
+                return {"PlaceCard": [hand_index, side]}
+    if view["player_pile"] and None in view["player_hand"]:
+        return "DrawCard"
+    # Rooms that only flip automatically refuse votes; don't keep trying.
+    flip_refused = message["action"] == {"MoveRejected": "ManualFlipDisabled"}
+    if not view["flip_requested"] and not flip_refused:
+        return "Flip"
+    return None
+
+
+# Every message gets exactly one line back, `null` when there's nothing to do.
+for line in sys.stdin:
+    print(json.dumps(decide(json.loads(line))), flush=True)
@@ -0,0 +1,97 @@
+//! Seat external bots at a real game session, in real time, without a client.
+//!
+//! `bot_host [--move-limit MS] [--games N] <bot> <bot>` where each bot is a
+//! built-in one (`greedy`, `blocking`, `random`, `easy`, `medium`, `hard`) or
+//! the command line of a program speaking the stdio protocol, such as
+//! `"python3 my_bot.py"`. Games are stored in the history database like any
+//! other, and the bots swap seats every game.
+
+use anyhow::{bail, Context, Result};
+use speed_card_ws::{
+    bot::{Blocking, BotLevel, ExternalBot, Greedy, Random, Strategy},
+    game_session::{start_game, Participant, SessionRegistry},
+    history::GameHistory,
+    FlipMode, RoomSettings,
+};
+use tokio::time::Duration;
+
+const USAGE: &str = "usage: bot_host [--move-limit MS] [--games N] <bot> <bot>
+bots: greedy, blocking, random, easy, medium, hard, or a command line";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut games: u32 = 1;
+    let mut move_limit = ExternalBot::DEFAULT_MOVE_LIMIT;
+    let mut bots = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => games = args.next().context(USAGE)?.parse()?,
+            "--move-limit" => {
+                move_limit = Duration::from_millis(args.next().context(USAGE)?.parse()?)
+            }
+            _ => bots.push(arg),
+        }
+    }
+    let [first, second] = bots.as_slice() else {
+        bail!(USAGE);
+    };
+    // Check both command lines before playing anything.
+    participant(first, move_limit)?;
+    participant(second, move_limit)?;
+
+    let history = GameHistory::open_configured()?;
+    // Nobody is watching the table, so don't wait on bots that never vote to flip.
+    let settings = RoomSettings {
+        flip_mode: FlipMode::Both,
+        ..RoomSettings::default()
+    };
+    let mut wins = [0; 2];
+    for game in 0..games {
+        let seating = if game % 2 == 0 { [0, 1] } else { [1, 0] };
+        let participants = seating
+            .map(|index| participant(&bots[index], move_limit).unwrap())
+            .into();
+        let outcome = start_game(
+            participants,
+            None,
+            settings.clone(),
+            SessionRegistry::default(),
+            history.clone(),
+        )
+        .await?;
+
+        let winner = seating[outcome.winner as usize];
+        wins[winner] += 1;
+        println!(
+            "Game {}: {} won ({:?}) after {:.1}s, seed {}",
+            game + 1,
+            bots[winner],
+            outcome.end,
+            outcome.duration.as_secs_f64(),
+            outcome.seed
+        );
+    }
+
+    for (index, bot) in bots.iter().enumerate() {
+        println!("{bot}: {} of {games} games", wins[index]);
+    }
+    Ok(())
+}
+
+fn participant(bot: &str, move_limit: Duration) -> Result<Participant> {
+    let strategy: Box<dyn Strategy> = match bot {
+        "greedy" => Box::new(Greedy),
+        "blocking" => Box::new(Blocking),
+        "random" => Box::new(Random),
+        "easy" => BotLevel::Easy.strategy(),
+        "medium" => BotLevel::Medium.strategy(),
+        "hard" => BotLevel::Hard.strategy(),
+        command_line => {
+            let bot = ExternalBot::from_command_line(command_line, move_limit)?;
+            return Ok(Participant::External(bot));
+        }
+    };
+    Ok(Participant::Bot(strategy))
+}
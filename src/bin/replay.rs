@@ -0,0 +1,90 @@
+//! Audit a recorded game.
+//!
+//! `replay <file>` re-runs a replay file against a freshly dealt table and
+//! reports how it ended, failing if any move is illegal or the recorded outcome
+//! doesn't match. `replay export <game id> [file]` writes a game from the
+//! history database (`SPEED_DB_PATH`) out as a replay file.
+
+use std::{
+    fs::File,
+    io::{self, BufReader},
+};
+
+use anyhow::{bail, Context, Result};
+use speed_card_ws::{game_logic::Card, history::GameHistory, replay::ReplayFile};
+
+const USAGE: &str = "usage: replay <file> | replay export <game id> [file]";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["export", game_id] => export(game_id, None).await,
+        ["export", game_id, path] => export(game_id, Some(path)).await,
+        [path] => verify(path),
+        _ => bail!(USAGE),
+    }
+}
+
+async fn export(game_id: &str, path: Option<&str>) -> Result<()> {
+    let game_id: i64 = game_id
+        .parse()
+        .with_context(|| format!("{game_id} is not a game id"))?;
+    let log = GameHistory::open_configured()?
+        .game_log(game_id)
+        .await?
+        .with_context(|| format!("no game with id {game_id}"))?;
+    let file = ReplayFile::from_log(&log);
+
+    match path {
+        Some(path) => file.write(File::create(path)?),
+        None => file.write(io::stdout().lock()),
+    }
+}
+
+fn verify(path: &str) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("could not open {path}"))?;
+    let replay = ReplayFile::read(BufReader::new(file))?;
+    let timeline = replay
+        .verify()
+        .with_context(|| format!("{path} does not replay"))?;
+
+    let view = timeline
+        .table_after(timeline.move_count())
+        .get_spectator_view(true);
+    println!(
+        "Seed {}, {} moves replayed",
+        replay.header.seed,
+        timeline.move_count()
+    );
+    let active: Vec<String> = view.active_cards.into_iter().map(card_name).collect();
+    println!("Active cards: {}", active.join(" | "));
+    for seat in view.players {
+        let hand: Vec<String> = seat
+            .cards
+            .unwrap_or_default()
+            .into_iter()
+            .map(card_name)
+            .collect();
+        let pile = if seat.pile { "cards left" } else { "empty" };
+        println!("{:?} hand: {}; pile: {pile}", seat.player, hand.join(", "));
+    }
+
+    match replay.outcome {
+        Some(outcome) => println!("Winner: {:?} ({:?})", outcome.winner, outcome.end),
+        None => println!("Winner: none, the game never finished"),
+    }
+    Ok(())
+}
+
+fn card_name(card: Option<Card>) -> String {
+    match card {
+        Some(card) => format!("{:?} of {:?}", card.rank, card.suit),
+        None => "-".to_string(),
+    }
+}
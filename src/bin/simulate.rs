@@ -0,0 +1,154 @@
+//! Pit two bots against each other over many games and report how they went.
+//!
+//! `simulate [--games N] [--seed S] [--variant house|classic] [--rules JSON]
+//! <bot> <bot>` where each bot is one of the reference strategies (`greedy`,
+//! `blocking`, `random`), which act instantly, or a lobby bot level (`easy`,
+//! `medium`, `hard`). `--rules` takes a `RulesConfig` as JSON, naming only the
+//! fields that change, like a room's rules. Game `i` is dealt from seed `S + i`
+//! and the bots swap seats every game, so the same arguments always give the
+//! same report.
+
+use anyhow::{bail, Context, Result};
+use speed_card_ws::{
+    bot::{Blocking, BotLevel, Greedy, Random, Strategy},
+    game_logic::{RulesConfig, SpeedTable, Variant},
+    simulation::{simulate_game, GameReport},
+};
+
+const USAGE: &str =
+    "usage: simulate [--games N] [--seed S] [--variant house|classic] [--rules JSON] <bot> <bot>
+bots: greedy, blocking, random, easy, medium, hard";
+
+struct Contestant {
+    name: String,
+    wins: u32,
+}
+
+fn main() -> Result<()> {
+    let mut games: u64 = 1000;
+    let mut base_seed: u64 = 0;
+    let mut rules = RulesConfig::default();
+    let mut names = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => games = args.next().context(USAGE)?.parse()?,
+            "--seed" => base_seed = args.next().context(USAGE)?.parse()?,
+            "--variant" => {
+                let variant = match args.next().context(USAGE)?.as_str() {
+                    "house" => Variant::House,
+                    "classic" => Variant::Classic,
+                    _ => bail!(USAGE),
+                };
+                rules = RulesConfig::for_variant(variant);
+            }
+            "--rules" => {
+                rules = serde_json::from_str(&args.next().context(USAGE)?)
+                    .context("--rules isn't a RulesConfig")?;
+            }
+            _ => names.push(arg),
+        }
+    }
+    let [first, second] = names.as_slice() else {
+        bail!(USAGE);
+    };
+    rules.validate().context("--rules can't be dealt")?;
+    if rules.players != 2 {
+        bail!(
+            "simulated games are one on one, not {} players",
+            rules.players
+        );
+    }
+    // Check both names before playing anything.
+    strategy(first)?;
+    strategy(second)?;
+    let mut contestants = [first, second].map(|name| Contestant {
+        name: name.clone(),
+        wins: 0,
+    });
+
+    let mut reports = Vec::new();
+    for game in 0..games {
+        // Swap seats every game so neither bot keeps whatever edge a seat has.
+        let seating = if game % 2 == 0 { [0, 1] } else { [1, 0] };
+        // Strategies keep state during a game, so each game gets fresh ones.
+        let mut strategies = seating.map(|index| strategy(&contestants[index].name).unwrap());
+        let [p1, p2] = &mut strategies;
+        let table = SpeedTable::with_rules(rules, base_seed.wrapping_add(game))?;
+        let report = simulate_game(table, [p1.as_mut(), p2.as_mut()]);
+        if let Some(winner) = report.winner {
+            contestants[seating[winner as usize]].wins += 1;
+        }
+        reports.push(report);
+    }
+
+    print_report(&contestants, &reports, base_seed);
+    Ok(())
+}
+
+fn strategy(name: &str) -> Result<Box<dyn Strategy>> {
+    Ok(match name {
+        "greedy" => Box::new(Greedy),
+        "blocking" => Box::new(Blocking),
+        "random" => Box::new(Random),
+        "easy" => BotLevel::Easy.strategy(),
+        "medium" => BotLevel::Medium.strategy(),
+        "hard" => BotLevel::Hard.strategy(),
+        _ => bail!("unknown bot {name}\n{USAGE}"),
+    })
+}
+
+fn print_report(contestants: &[Contestant; 2], reports: &[GameReport], base_seed: u64) {
+    let games = reports.len().max(1) as f64;
+    let mean = |field: fn(&GameReport) -> f64| reports.iter().map(field).sum::<f64>() / games;
+
+    println!("{} games from base seed {base_seed}", reports.len());
+    for (seat, contestant) in contestants.iter().enumerate() {
+        println!(
+            "  bot {} ({}): {} wins, {:.1}%",
+            seat + 1,
+            contestant.name,
+            contestant.wins,
+            100.0 * contestant.wins as f64 / games
+        );
+    }
+    let unfinished = reports
+        .iter()
+        .filter(|report| report.winner.is_none())
+        .count();
+    println!("  unfinished: {unfinished}");
+
+    let actions = reports.iter().map(|report| report.actions);
+    println!(
+        "Actions per game: mean {:.1}, min {}, max {}",
+        mean(|report| report.actions as f64),
+        actions.clone().min().unwrap_or(0),
+        actions.max().unwrap_or(0)
+    );
+    println!(
+        "Rejected actions per game: mean {:.1}",
+        mean(|report| report.rejected as f64)
+    );
+    println!(
+        "Middle flips per game: mean {:.1}",
+        mean(|report| report.middle_flips as f64)
+    );
+    println!(
+        "Reshuffles per game: mean {:.2}",
+        mean(|report| report.reshuffles as f64)
+    );
+    let with_stalemate = reports
+        .iter()
+        .filter(|report| report.stalemates > 0)
+        .count();
+    println!(
+        "Stalemates per game: mean {:.1}, in {:.1}% of games",
+        mean(|report| report.stalemates as f64),
+        100.0 * with_stalemate as f64 / games
+    );
+    println!(
+        "Simulated game length: mean {:.1}s",
+        mean(|report| report.duration.as_secs_f64())
+    );
+}
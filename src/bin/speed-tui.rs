@@ -0,0 +1,538 @@
+//! Play Speed from a terminal.
+//!
+//! `speed-tui [--server URL] [quick | create | join <code> | bot <level> | resume <token>]`
+//! connects to a server (by default the one `cargo run` starts locally), asks
+//! to be seated and draws the table as it changes. The number keys put hand
+//! slots 1, 2, 3... on the left pile, and the home row keys `a s d f...` put
+//! them on the right one. Bigger tables add the `q w e r...` and
+//! `z x c v...` rows for their third and fourth piles.
+
+use std::io::{stdout, Write};
+
+use anyhow::{bail, Context, Result};
+use crossterm::{
+    cursor::{Hide, MoveTo, MoveToNextLine, Show},
+    event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use speed_card_ws::{
+    bot::BotLevel,
+    game_logic::{Card, PlayerView, Rank, SeatView, Side, Suit, MAX_HAND_SIZE},
+    JoinRequest, JoinResponse, PlayerAction, RejectReason, RoomSettings, ServerAction,
+    ServerMessage,
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+const USAGE: &str = "usage: speed-tui [--server URL] [quick | create | join <code> | bot <easy|medium|hard> | resume <token>]";
+
+const DEFAULT_SERVER: &str = "ws://127.0.0.1:8080";
+
+/// Keys that place each hand slot on each pile, indexed by `Side as usize`.
+const PILE_KEYS: [[char; MAX_HAND_SIZE]; 4] = [
+    ['1', '2', '3', '4', '5', '6', '7', '8'],
+    ['a', 's', 'd', 'f', 'g', 'h', 'j', 'k'],
+    ['q', 'w', 'e', 'r', 't', 'y', 'u', 'i'],
+    ['z', 'x', 'c', 'v', 'b', 'n', 'm', ','],
+];
+
+const HELP: &str = "1 2 3 4...: place on left   a s d f...: place on right   q w e r... / z x c v...: third / fourth pile   space: draw   enter: flip   esc: quit";
+
+/// The server answers a join request with a `JoinResponse` and then sends
+/// `ServerMessage`s once the game starts.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Incoming {
+    Game(Box<ServerMessage>),
+    Join(JoinResponse),
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut server = DEFAULT_SERVER.to_string();
+    let mut words = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server = args.next().context(USAGE)?,
+            _ => words.push(arg),
+        }
+    }
+    let request = join_request(&words)?;
+
+    let url = format!("{}/join", server.trim_end_matches('/'));
+    let (mut connection, _) = connect_async(url::Url::parse(&url)?)
+        .await
+        .with_context(|| format!("could not connect to {server}"))?;
+    connection
+        .send(Message::Text(serde_json::to_string(&request)?))
+        .await?;
+
+    let _terminal = RawTerminal::enter()?;
+    let mut keys = EventStream::new();
+    let mut screen = Screen::new(server);
+    let mut connected = true;
+
+    loop {
+        screen.draw()?;
+        tokio::select! {
+            message = connection.next(), if connected => match message {
+                Some(Ok(Message::Text(text))) => screen.receive(&text),
+                // Pings are answered by the library.
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => {
+                    connected = false;
+                    screen.disconnected();
+                }
+            },
+            event = keys.next() => {
+                let Some(Ok(Event::Key(key))) = event else { continue };
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if is_quit(key) {
+                    break;
+                }
+                if let Some(action) = key_action(key.code) {
+                    if connected && screen.view.is_some() {
+                        let text = serde_json::to_string(&action)?;
+                        connection.send(Message::Text(text)).await?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn join_request(words: &[String]) -> Result<JoinRequest> {
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    Ok(match words.as_slice() {
+        [] | ["quick"] => JoinRequest::QuickMatch,
+        ["create"] => JoinRequest::CreateRoom(RoomSettings::default()),
+        ["join", code] => JoinRequest::JoinRoom(code.to_uppercase()),
+        ["resume", token] => JoinRequest::Resume(token.to_string()),
+        ["bot", "easy"] => JoinRequest::Bot(BotLevel::Easy),
+        ["bot", "medium"] => JoinRequest::Bot(BotLevel::Medium),
+        ["bot", "hard"] => JoinRequest::Bot(BotLevel::Hard),
+        _ => bail!(USAGE),
+    })
+}
+
+fn is_quit(key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        KeyCode::Esc => true,
+        _ => false,
+    }
+}
+
+fn key_action(code: KeyCode) -> Option<PlayerAction> {
+    let key = match code {
+        KeyCode::Enter => return Some(PlayerAction::Flip),
+        KeyCode::Char(' ') => return Some(PlayerAction::DrawCard),
+        KeyCode::Char(key) => key,
+        _ => return None,
+    };
+    Side::ALL
+        .into_iter()
+        .zip(PILE_KEYS)
+        .find_map(|(side, keys)| {
+            let hand_index = keys.iter().position(|&pile_key| pile_key == key)?;
+            Some(PlayerAction::PlaceCard(hand_index, side))
+        })
+}
+
+/// Everything the terminal shows.
+struct Screen {
+    server: String,
+    room_code: Option<String>,
+    view: Option<PlayerView>,
+    status: String,
+    finished: bool,
+}
+
+impl Screen {
+    fn new(server: String) -> Screen {
+        Screen {
+            server,
+            room_code: None,
+            view: None,
+            status: "Waiting for an opponent".to_string(),
+            finished: false,
+        }
+    }
+
+    fn receive(&mut self, text: &str) {
+        match serde_json::from_str(text) {
+            Ok(Incoming::Game(message)) => {
+                self.status = describe_action(&message.action);
+                if let Some(token) = &message.resume_token {
+                    self.status +=
+                        &format!(" If the connection drops, rejoin with `resume {token}`.");
+                }
+                self.finished |= matches!(message.action, ServerAction::GameOver(_));
+                self.view = Some(message.player_view);
+            }
+            Ok(Incoming::Join(response)) => {
+                self.status = describe_join_response(&response);
+                if let JoinResponse::RoomCreated(code) = response {
+                    self.room_code = Some(code);
+                }
+            }
+            Err(e) => self.status = format!("Could not read a message from the server: {e}"),
+        }
+    }
+
+    fn disconnected(&mut self) {
+        if !self.finished {
+            self.status = "Lost the connection to the server".to_string();
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut title = format!("Speed  {}", self.server);
+        if let Some(code) = &self.room_code {
+            title += &format!("  room {code}");
+        }
+        let mut lines = vec![title, String::new()];
+
+        if let Some(view) = &self.view {
+            for opponent in &view.opponents {
+                lines.push(opponent_line(opponent, view.opponents.len() > 1));
+            }
+            if let Some(partner) = &view.partner {
+                lines.push(partner_line(partner));
+            }
+            lines.push(String::new());
+            let middle: Vec<String> = view
+                .middle_piles
+                .iter()
+                .zip(&view.active_cards)
+                .map(|(&middle, &active)| format!("{} [{}]", pile(middle), card_or_blank(active)))
+                .collect();
+            lines.push(format!("Middle    {}", middle.join("   ")));
+            lines.push(String::new());
+            let hand: Vec<String> = view
+                .player_hand
+                .iter()
+                .map(|&card| format!("[{}]", card_or_blank(card)))
+                .collect();
+            lines.push(format!(
+                "You       {}  {}{}",
+                hand.join(" "),
+                pile(view.player_pile),
+                if view.flip_requested {
+                    "  voted to flip"
+                } else {
+                    ""
+                }
+            ));
+            let keys: Vec<String> = (0..view.player_hand.len())
+                .map(|slot| {
+                    let keys: Vec<String> = PILE_KEYS[..view.active_cards.len()]
+                        .iter()
+                        .map(|keys| keys[slot].to_string())
+                        .collect();
+                    format!(" {} ", keys.join("/"))
+                })
+                .collect();
+            lines.push(format!("          {}", keys.join(" ")));
+            lines.push(String::new());
+        }
+
+        lines.push(self.status.clone());
+        lines.push(String::new());
+        lines.push(HELP.to_string());
+        lines
+    }
+
+    fn draw(&self) -> Result<()> {
+        let mut out = stdout();
+        queue!(out, MoveTo(0, 0), Clear(ClearType::All))?;
+        for line in self.lines() {
+            queue!(out, Print(line), MoveToNextLine(1))?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// One line per opponent: their hand face down, their pile and whether they
+/// want to flip. Bigger tables name opponents by seat.
+fn opponent_line(opponent: &SeatView, by_seat: bool) -> String {
+    let hand: Vec<&str> = opponent
+        .hand
+        .iter()
+        .map(|&held| if held { "[###]" } else { "[   ]" })
+        .collect();
+    let name = if by_seat {
+        format!("Player {}", opponent.player as usize + 1)
+    } else {
+        "Opponent".to_string()
+    };
+    format!(
+        "{name:<10}{}  {}{}",
+        hand.join(" "),
+        pile(opponent.pile),
+        if opponent.flip_requested {
+            "  wants to flip"
+        } else {
+            ""
+        }
+    )
+}
+
+/// Your partner's hand is face up, so it's shown like your own.
+fn partner_line(partner: &SeatView) -> String {
+    let hand: Vec<String> = partner
+        .cards
+        .iter()
+        .flatten()
+        .map(|&card| format!("[{}]", card_or_blank(card)))
+        .collect();
+    format!(
+        "Partner   {}  {}{}",
+        hand.join(" "),
+        pile(partner.pile),
+        if partner.flip_requested {
+            "  wants to flip"
+        } else {
+            ""
+        }
+    )
+}
+
+fn pile(present: bool) -> &'static str {
+    if present {
+        "(pile)"
+    } else {
+        "(    )"
+    }
+}
+
+/// Three characters wide, like ` 7♥`, `10♠` or ` ★R` for the red joker.
+fn card_or_blank(card: Option<Card>) -> String {
+    let Some(card) = card else {
+        return "   ".to_string();
+    };
+    let rank = match card.rank {
+        Rank::Ace => "A",
+        Rank::Two => "2",
+        Rank::Three => "3",
+        Rank::Four => "4",
+        Rank::Five => "5",
+        Rank::Six => "6",
+        Rank::Seven => "7",
+        Rank::Eight => "8",
+        Rank::Nine => "9",
+        Rank::Ten => "10",
+        Rank::Jack => "J",
+        Rank::Queen => "Q",
+        Rank::King => "K",
+        Rank::Joker => "★",
+    };
+    let suit = match card.suit {
+        Suit::Diamonds => '♦',
+        Suit::Spades => '♠',
+        Suit::Clubs => '♣',
+        Suit::Hearts => '♥',
+        Suit::Red => 'R',
+        Suit::Black => 'B',
+    };
+    format!("{rank:>2}{suit}")
+}
+
+fn describe_action(action: &ServerAction) -> String {
+    match action {
+        ServerAction::SetBoard => "Game on!".to_string(),
+        ServerAction::NormalMove => String::new(),
+        ServerAction::GameWon => "You won!".to_string(),
+        ServerAction::GameLost => "You lost.".to_string(),
+        ServerAction::Resumed => "Back in the game".to_string(),
+        ServerAction::OpponentDisconnected => {
+            "An opponent disconnected, waiting for them to come back".to_string()
+        }
+        ServerAction::OpponentReconnected => "The opponent is back".to_string(),
+        ServerAction::GameOver(summary) => format!(
+            "{} after {:.1}s: you played {} cards, your opponents {}, with {} middle flips. Press esc to quit.",
+            if summary.won { "You won" } else { "You lost" },
+            summary.duration_ms as f64 / 1000.0,
+            summary.cards_played,
+            summary.opponent_cards_played,
+            summary.middle_flips
+        ),
+        ServerAction::MoveRejected(reason) => format!("Move rejected: {}", describe_rejection(*reason)),
+        ServerAction::FlipRequested => {
+            "An opponent wants to flip the middle cards (enter)".to_string()
+        }
+        ServerAction::StalemateCountdown(seconds) => {
+            format!("Nobody can move, flipping in {seconds}s")
+        }
+    }
+}
+
+fn describe_rejection(reason: RejectReason) -> &'static str {
+    match reason {
+        RejectReason::NoCardToDraw => "your draw pile is empty",
+        RejectReason::HandAlreadyFull => "your hand is already full",
+        RejectReason::NoCardToPlace => "that hand slot is empty",
+        RejectReason::NoCardToPlaceOn => "there is no card on that pile",
+        RejectReason::NotAdjacentCard => "the card isn't one above or below the pile",
+        RejectReason::FlipAlreadyRequested => "you already voted to flip",
+        RejectReason::ManualFlipDisabled => "this room only flips automatically",
+    }
+}
+
+fn describe_join_response(response: &JoinResponse) -> String {
+    match response {
+        JoinResponse::RoomCreated(code) => {
+            format!("Room {code} created, waiting for the other players to join it")
+        }
+        JoinResponse::RoomJoined { seated, players } => {
+            format!("Joined the room, {seated} of {players} players are here")
+        }
+        JoinResponse::RoomLeft { seated, players } => {
+            format!("Someone left the room, {seated} of {players} players are here")
+        }
+        JoinResponse::RoomNotFound => "There is no room with that code".to_string(),
+        JoinResponse::RoomExpired => "That room has expired".to_string(),
+        JoinResponse::ResumeFailed => "That game can't be resumed".to_string(),
+        JoinResponse::InvalidRequest => "The server didn't understand the request".to_string(),
+        JoinResponse::ReplayNotFound => "There is no such game to replay".to_string(),
+        JoinResponse::InvalidRules(reason) => format!("The room's rules don't work: {reason}"),
+    }
+}
+
+/// Raw mode on an alternate screen for as long as this is alive.
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> Result<RawTerminal> {
+        enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen, Hide)?;
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), Show, LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speed_card_ws::game_logic::{Player, RulesConfig, SpeedTable, Variant};
+
+    #[test]
+    fn test_key_actions() {
+        assert_eq!(
+            key_action(KeyCode::Char('2')),
+            Some(PlayerAction::PlaceCard(1, Side::LEFT))
+        );
+        assert_eq!(
+            key_action(KeyCode::Char('f')),
+            Some(PlayerAction::PlaceCard(3, Side::RIGHT))
+        );
+        assert_eq!(
+            key_action(KeyCode::Char('w')),
+            Some(PlayerAction::PlaceCard(1, Side::THIRD))
+        );
+        assert_eq!(
+            key_action(KeyCode::Char('v')),
+            Some(PlayerAction::PlaceCard(3, Side::FOURTH))
+        );
+        assert_eq!(key_action(KeyCode::Char(' ')), Some(PlayerAction::DrawCard));
+        assert_eq!(key_action(KeyCode::Enter), Some(PlayerAction::Flip));
+        assert_eq!(key_action(KeyCode::Char('p')), None);
+    }
+
+    #[test]
+    fn test_screen_shows_table_and_rejections() {
+        let mut view = SpeedTable::from_seed(0).get_player_view(Player::PLAYER1);
+        view.player_hand[0] = Some(Card::new(Rank::Ten, Suit::Hearts));
+        view.active_cards[0] = Some(Card::new(Rank::Ace, Suit::Spades));
+        let message = ServerMessage {
+            action: ServerAction::MoveRejected(RejectReason::NotAdjacentCard),
+            player_view: view,
+            resume_token: None,
+        };
+
+        let mut screen = Screen::new(DEFAULT_SERVER.to_string());
+        screen.receive(&serde_json::to_string(&message).unwrap());
+        let lines = screen.lines();
+        assert!(lines.iter().any(|line| line.starts_with("You       [10♥]")));
+        assert!(lines.iter().any(|line| line.contains("[ A♠]")));
+        assert!(lines
+            .contains(&"Move rejected: the card isn't one above or below the pile".to_string()));
+
+        screen.receive(
+            &serde_json::to_string(&JoinResponse::RoomCreated("K7QXM".to_string())).unwrap(),
+        );
+        assert!(screen.lines()[0].ends_with("room K7QXM"));
+    }
+
+    #[test]
+    fn test_screen_shows_every_opponent() {
+        let rules = RulesConfig::for_players(Variant::House, 3);
+        let table = SpeedTable::with_rules(rules, 0).unwrap();
+        let message = ServerMessage {
+            action: ServerAction::SetBoard,
+            player_view: table.get_player_view(Player::PLAYER2),
+            resume_token: None,
+        };
+
+        let mut screen = Screen::new(DEFAULT_SERVER.to_string());
+        screen.receive(&serde_json::to_string(&message).unwrap());
+        let lines = screen.lines();
+        let position = |prefix: &str| lines.iter().position(|line| line.starts_with(prefix));
+        assert!(position("Player 3").unwrap() < position("Player 1").unwrap());
+        assert_eq!(
+            lines
+                .iter()
+                .find(|line| line.starts_with("Middle"))
+                .unwrap(),
+            "Middle    (pile) [   ]   (pile) [   ]   (pile) [   ]"
+        );
+        assert!(lines.iter().any(|line| line.contains(" 1/a/q ")));
+    }
+
+    #[test]
+    fn test_screen_shows_partner_hand() {
+        let rules = RulesConfig {
+            teams: true,
+            ..RulesConfig::for_players(Variant::House, 4)
+        };
+        let mut table = SpeedTable::with_rules(rules, 0).unwrap();
+        table.player_draw_card(Player::PLAYER4).unwrap();
+        let view = table.get_player_view(Player::PLAYER2);
+        let drawn = view.partner.as_ref().unwrap().cards.as_ref().unwrap()[0];
+        let message = ServerMessage {
+            action: ServerAction::SetBoard,
+            player_view: view,
+            resume_token: None,
+        };
+
+        let mut screen = Screen::new(DEFAULT_SERVER.to_string());
+        screen.receive(&serde_json::to_string(&message).unwrap());
+        let lines = screen.lines();
+        let partner = format!("Partner   [{}] [   ]", card_or_blank(drawn));
+        assert!(lines.iter().any(|line| line.starts_with(&partner)));
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.starts_with("Player"))
+                .count(),
+            2
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use rand::RngCore;
+
+use super::strategy::{
+    can_draw, can_vote_flip, playable_cards, Decision, Strategy, StrategyContext,
+};
+use crate::{game_logic::PlayerView, PlayerAction};
+
+/// Plays in runs. A playable card is held back until the player has another
+/// card to follow it with, so the opponent gets no opening on the pile in
+/// between. It stops holding once there is nothing left to draw or an
+/// opponent is down to their last few cards.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Blocking;
+
+impl Strategy for Blocking {
+    fn decide(
+        &mut self,
+        view: &PlayerView,
+        context: &StrategyContext,
+        _rng: &mut dyn RngCore,
+    ) -> Decision {
+        let placements = playable_cards(view);
+
+        for &(hand_index, side) in &placements {
+            let mut after = view.clone();
+            after.active_cards[side as usize] = after.player_hand[hand_index].take();
+            if !playable_cards(&after).is_empty() {
+                return Decision::Act(PlayerAction::PlaceCard(hand_index, side));
+            }
+        }
+
+        let opponent_finishing = view.opponents.iter().any(|opponent| !opponent.pile);
+        if can_draw(view) && !opponent_finishing {
+            return Decision::Act(PlayerAction::DrawCard);
+        }
+        if let Some(&(hand_index, side)) = placements.first() {
+            return Decision::Act(PlayerAction::PlaceCard(hand_index, side));
+        }
+        if can_draw(view) {
+            return Decision::Act(PlayerAction::DrawCard);
+        }
+        if can_vote_flip(view, context) {
+            return Decision::Act(PlayerAction::Flip);
+        }
+        Decision::WaitForChange
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::game_logic::{Card, Player, Rank, Side, SpeedTable, Suit};
+
+    #[test]
+    fn test_holds_cards_it_cannot_follow() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let context = StrategyContext::default();
+        let mut view = SpeedTable::from_seed(0).get_player_view(Player::PLAYER1);
+        view.player_hand = vec![None; 4];
+        view.player_hand[0] = Some(Card::new(Rank::Four, Suit::Clubs));
+        view.active_cards = vec![
+            Some(Card::new(Rank::Nine, Suit::Spades)),
+            Some(Card::new(Rank::Five, Suit::Hearts)),
+        ];
+
+        // The Four leaves nothing to follow it, so draw instead.
+        view.player_pile = true;
+        view.opponents[0].pile = true;
+        assert_eq!(
+            Blocking.decide(&view, &context, &mut rng),
+            Decision::Act(PlayerAction::DrawCard)
+        );
+
+        // A Three would follow, so start the run.
+        view.player_hand[1] = Some(Card::new(Rank::Three, Suit::Hearts));
+        assert_eq!(
+            Blocking.decide(&view, &context, &mut rng),
+            Decision::Act(PlayerAction::PlaceCard(0, Side::RIGHT))
+        );
+
+        // With the opponent about to finish, play whatever it can.
+        view.player_hand[1] = None;
+        view.opponents[0].pile = false;
+        assert_eq!(
+            Blocking.decide(&view, &context, &mut rng),
+            Decision::Act(PlayerAction::PlaceCard(0, Side::RIGHT))
+        );
+    }
+}
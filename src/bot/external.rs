@@ -0,0 +1,33 @@
+use anyhow::{bail, Result};
+use tokio::time::Duration;
+
+/// A bot written in any language, run as a subprocess that talks over stdio.
+///
+/// Every `ServerMessage` for its seat is written to the process's stdin as one
+/// line of JSON, and it answers each one with a line on stdout: a
+/// `PlayerAction` such as `"DrawCard"` or `{"PlaceCard":[0,"LEFT"]}`, or
+/// `null` to do nothing. A bot that exits, writes anything else, or takes
+/// longer than `move_limit` to answer forfeits the game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalBot {
+    pub program: String,
+    pub args: Vec<String>,
+    pub move_limit: Duration,
+}
+
+impl ExternalBot {
+    pub const DEFAULT_MOVE_LIMIT: Duration = Duration::from_millis(500);
+
+    /// Split a command line such as `python3 bots/greedy.py` on whitespace.
+    pub fn from_command_line(command_line: &str, move_limit: Duration) -> Result<ExternalBot> {
+        let mut words = command_line.split_whitespace().map(str::to_string);
+        let Some(program) = words.next() else {
+            bail!("empty bot command");
+        };
+        Ok(ExternalBot {
+            program,
+            args: words.collect(),
+            move_limit,
+        })
+    }
+}
@@ -0,0 +1,78 @@
+use rand::RngCore;
+
+use super::strategy::{
+    can_draw, can_vote_flip, playable_cards, Decision, Strategy, StrategyContext,
+};
+use crate::{game_logic::PlayerView, PlayerAction};
+
+/// Plays a card whenever it can, draws when it can't, and votes to flip when
+/// there's nothing else left to do.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Greedy;
+
+impl Strategy for Greedy {
+    fn decide(
+        &mut self,
+        view: &PlayerView,
+        context: &StrategyContext,
+        _rng: &mut dyn RngCore,
+    ) -> Decision {
+        if let Some(&(hand_index, side)) = playable_cards(view).first() {
+            Decision::Act(PlayerAction::PlaceCard(hand_index, side))
+        } else if can_draw(view) {
+            Decision::Act(PlayerAction::DrawCard)
+        } else if can_vote_flip(view, context) {
+            Decision::Act(PlayerAction::Flip)
+        } else {
+            Decision::WaitForChange
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::game_logic::{Card, Player, Rank, Side, SpeedTable, Suit};
+
+    #[test]
+    fn test_greedy_decisions() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut context = StrategyContext::default();
+        let mut view = SpeedTable::from_seed(0).get_player_view(Player::PLAYER1);
+
+        assert_eq!(
+            Greedy.decide(&view, &context, &mut rng),
+            Decision::Act(PlayerAction::DrawCard)
+        );
+
+        view.player_pile = false;
+        view.player_hand[2] = Some(Card::new(Rank::Four, Suit::Clubs));
+        view.active_cards = vec![
+            Some(Card::new(Rank::Nine, Suit::Spades)),
+            Some(Card::new(Rank::Five, Suit::Hearts)),
+        ];
+        assert_eq!(
+            Greedy.decide(&view, &context, &mut rng),
+            Decision::Act(PlayerAction::PlaceCard(2, Side::RIGHT))
+        );
+
+        view.active_cards[1] = Some(Card::new(Rank::Jack, Suit::Hearts));
+        assert_eq!(
+            Greedy.decide(&view, &context, &mut rng),
+            Decision::Act(PlayerAction::Flip)
+        );
+        view.flip_requested = true;
+        assert_eq!(
+            Greedy.decide(&view, &context, &mut rng),
+            Decision::WaitForChange
+        );
+        view.flip_requested = false;
+        context.can_vote_flip = false;
+        assert_eq!(
+            Greedy.decide(&view, &context, &mut rng),
+            Decision::WaitForChange
+        );
+    }
+}
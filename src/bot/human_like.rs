@@ -0,0 +1,154 @@
+use rand::{seq::SliceRandom, Rng, RngCore};
+use tokio::time::Duration;
+
+use super::{
+    strategy::{Decision, Strategy, StrategyContext},
+    BotConfig,
+};
+use crate::{
+    game_logic::{PlayerView, Side},
+    PlayerAction,
+};
+
+/// Plays another strategy's moves at a human pace: it takes the configured
+/// reaction time to respond to the table, and now and then fumbles a card.
+pub struct HumanLike<S> {
+    config: BotConfig,
+    inner: S,
+    /// When, in game time, the current reaction is over.
+    ready_at: Option<Duration>,
+}
+
+impl<S: Strategy> HumanLike<S> {
+    pub fn new(config: BotConfig, inner: S) -> HumanLike<S> {
+        HumanLike {
+            config,
+            inner,
+            ready_at: None,
+        }
+    }
+}
+
+impl<S: Strategy> Strategy for HumanLike<S> {
+    fn decide(
+        &mut self,
+        view: &PlayerView,
+        context: &StrategyContext,
+        rng: &mut dyn RngCore,
+    ) -> Decision {
+        // Changes that land while still reacting don't restart the clock.
+        let ready_at = *self
+            .ready_at
+            .get_or_insert_with(|| context.elapsed + self.config.reaction(rng));
+        if context.elapsed < ready_at {
+            return Decision::Wait(ready_at - context.elapsed);
+        }
+        self.ready_at = None;
+
+        let decision = self.inner.decide(view, context, rng);
+        let held: Vec<usize> = (0..view.player_hand.len())
+            .filter(|&hand_index| view.player_hand[hand_index].is_some())
+            .collect();
+        let fumbled = matches!(decision, Decision::Act(_))
+            && !held.is_empty()
+            && rng.gen_bool(self.config.error_rate.clamp(0.0, 1.0));
+        if fumbled {
+            let side = *Side::ALL[..view.active_cards.len()].choose(rng).unwrap();
+            return Decision::Act(PlayerAction::PlaceCard(*held.choose(rng).unwrap(), side));
+        }
+        decision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        bot::{BotLevel, Greedy},
+        game_logic::{
+            test_support::table_with, Card, Player, Rank, RulesConfig, SpeedTable, Suit, Variant,
+        },
+    };
+
+    #[test]
+    fn test_waits_out_reaction_then_acts() {
+        let config = BotConfig {
+            error_rate: 0.0,
+            ..BotLevel::Medium.config()
+        };
+        let mut bot = HumanLike::new(config, Greedy);
+        let mut rng = StdRng::seed_from_u64(3);
+        let view = SpeedTable::from_seed(0).get_player_view(Player::PLAYER1);
+        let mut context = StrategyContext {
+            elapsed: Duration::from_secs(10),
+            ..StrategyContext::default()
+        };
+
+        let Decision::Wait(delay) = bot.decide(&view, &context, &mut rng) else {
+            panic!("expected the bot to react first");
+        };
+        assert!(delay >= config.reaction_time - config.jitter);
+
+        // A change partway through doesn't restart the reaction.
+        context.elapsed += delay / 2;
+        assert_eq!(
+            bot.decide(&view, &context, &mut rng),
+            Decision::Wait(delay - delay / 2)
+        );
+
+        context.elapsed += delay - delay / 2;
+        assert_eq!(
+            bot.decide(&view, &context, &mut rng),
+            Decision::Act(PlayerAction::DrawCard)
+        );
+    }
+
+    #[test]
+    fn test_fumbles_a_held_card() {
+        let config = BotConfig {
+            reaction_time: Duration::ZERO,
+            jitter: Duration::ZERO,
+            error_rate: 1.0,
+        };
+        let mut bot = HumanLike::new(config, Greedy);
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut view = SpeedTable::from_seed(0).get_player_view(Player::PLAYER1);
+        view.player_hand[1] = Some(Card::new(Rank::King, Suit::Spades));
+        let context = StrategyContext::default();
+
+        let Decision::Act(PlayerAction::PlaceCard(hand_index, _)) =
+            bot.decide(&view, &context, &mut rng)
+        else {
+            panic!("expected a fumbled placement");
+        };
+        assert!(view.player_hand[hand_index].is_some());
+    }
+
+    #[test]
+    fn test_fumbles_onto_every_pile() {
+        let config = BotConfig {
+            reaction_time: Duration::ZERO,
+            jitter: Duration::ZERO,
+            error_rate: 1.0,
+        };
+        let mut bot = HumanLike::new(config, Greedy);
+        let mut rng = StdRng::seed_from_u64(3);
+        let rules = RulesConfig::for_players(Variant::Classic, 4);
+        let view = table_with(rules, 0).get_player_view(Player::PLAYER1);
+        let context = StrategyContext::default();
+
+        let mut sides = Vec::new();
+        for _ in 0..100 {
+            if let Decision::Act(PlayerAction::PlaceCard(_, side)) =
+                bot.decide(&view, &context, &mut rng)
+            {
+                if !sides.contains(&side) {
+                    sides.push(side);
+                }
+            }
+        }
+        assert_eq!(sides.len(), 4);
+    }
+}
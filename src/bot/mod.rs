@@ -0,0 +1,91 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::time::Duration;
+
+mod strategy;
+pub use strategy::{can_draw, can_vote_flip, playable_cards, Decision, Strategy, StrategyContext};
+
+mod blocking;
+pub use blocking::Blocking;
+
+mod external;
+pub use external::ExternalBot;
+
+mod greedy;
+pub use greedy::Greedy;
+
+mod human_like;
+pub use human_like::HumanLike;
+
+mod random;
+pub use random::Random;
+
+/// How hard a bot requested from the lobby plays: a greedy player at one of
+/// three human-like paces.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum BotLevel {
+    Easy,
+    Medium,
+    Hard,
+}
+
+/// How a bot plays: how long it takes to react to a change on the table, and
+/// how often it fumbles a move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BotConfig {
+    pub reaction_time: Duration,
+    /// Each reaction is up to this much quicker or slower than `reaction_time`.
+    pub jitter: Duration,
+    /// Chance, from 0 to 1, of playing a random hand card instead of a sensible move.
+    pub error_rate: f64,
+}
+
+impl BotLevel {
+    pub fn strategy(self) -> Box<dyn Strategy> {
+        Box::new(HumanLike::new(self.config(), Greedy))
+    }
+
+    pub fn config(self) -> BotConfig {
+        let (reaction_ms, jitter_ms, error_rate) = match self {
+            BotLevel::Easy => (1500, 500, 0.15),
+            BotLevel::Medium => (900, 300, 0.07),
+            BotLevel::Hard => (450, 150, 0.02),
+        };
+        BotConfig {
+            reaction_time: Duration::from_millis(reaction_ms),
+            jitter: Duration::from_millis(jitter_ms),
+            error_rate,
+        }
+    }
+}
+
+impl BotConfig {
+    /// How long to wait before the next move.
+    pub fn reaction(&self, rng: &mut (impl Rng + ?Sized)) -> Duration {
+        let jitter = self.jitter.as_secs_f64();
+        let offset = if jitter > 0.0 {
+            rng.gen_range(-jitter..=jitter)
+        } else {
+            0.0
+        };
+        Duration::from_secs_f64((self.reaction_time.as_secs_f64() + offset).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_reaction_stays_within_jitter() {
+        let config = BotLevel::Medium.config();
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let reaction = config.reaction(&mut rng);
+            assert!(reaction >= config.reaction_time - config.jitter);
+            assert!(reaction <= config.reaction_time + config.jitter);
+        }
+    }
+}
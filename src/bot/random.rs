@@ -0,0 +1,36 @@
+use rand::{seq::SliceRandom, RngCore};
+
+use super::strategy::{
+    can_draw, can_vote_flip, playable_cards, Decision, Strategy, StrategyContext,
+};
+use crate::{game_logic::PlayerView, PlayerAction};
+
+/// Picks uniformly among the actions its view shows to be legal. A baseline
+/// for other strategies to beat.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Random;
+
+impl Strategy for Random {
+    fn decide(
+        &mut self,
+        view: &PlayerView,
+        context: &StrategyContext,
+        rng: &mut dyn RngCore,
+    ) -> Decision {
+        let mut actions: Vec<PlayerAction> = playable_cards(view)
+            .into_iter()
+            .map(|(hand_index, side)| PlayerAction::PlaceCard(hand_index, side))
+            .collect();
+        if can_draw(view) {
+            actions.push(PlayerAction::DrawCard);
+        }
+        if can_vote_flip(view, context) {
+            actions.push(PlayerAction::Flip);
+        }
+
+        match actions.choose(rng) {
+            Some(&action) => Decision::Act(action),
+            None => Decision::WaitForChange,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use rand::RngCore;
+use tokio::time::Duration;
+
+use crate::{
+    game_logic::{PlayerView, Side},
+    PlayerAction, RejectReason,
+};
+
+/// Everything a strategy is told besides what's on the table.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StrategyContext {
+    /// Time since the game started.
+    pub elapsed: Duration,
+    /// Time since the table last changed.
+    pub since_last_change: Duration,
+    /// False once the game has refused a flip vote, e.g. in automatic flip rooms.
+    pub can_vote_flip: bool,
+    /// Why the strategy's last action was refused, until the table next changes.
+    pub last_rejection: Option<RejectReason>,
+}
+
+/// The start of a game: nothing has happened yet and flip votes are allowed.
+impl Default for StrategyContext {
+    fn default() -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            since_last_change: Duration::ZERO,
+            can_vote_flip: true,
+            last_rejection: None,
+        }
+    }
+}
+
+/// What a strategy wants to do next.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decision {
+    Act(PlayerAction),
+    /// Ask again after this long, or sooner if the table changes.
+    Wait(Duration),
+    /// Nothing to do until the table changes.
+    WaitForChange,
+}
+
+/// Decides a player's moves from what that player can see. Whoever drives the
+/// strategy asks it for a decision whenever the table changes, whenever one of
+/// its actions is refused, and whenever a `Wait` it asked for runs out.
+///
+/// Randomness should come from `rng` so that simulations can be replayed.
+pub trait Strategy: Send {
+    fn decide(
+        &mut self,
+        view: &PlayerView,
+        context: &StrategyContext,
+        rng: &mut dyn RngCore,
+    ) -> Decision;
+}
+
+/// Each `(hand_index, side)` placement the view shows to be legal.
+pub fn playable_cards(view: &PlayerView) -> Vec<(usize, Side)> {
+    let mut placements = Vec::new();
+    for (hand_index, card) in view.player_hand.iter().enumerate() {
+        let Some(card) = card else { continue };
+        for (side, active) in Side::ALL.into_iter().zip(&view.active_cards) {
+            if active.is_some_and(|active| card.is_adjacent_card(&active, &view.rules)) {
+                placements.push((hand_index, side));
+            }
+        }
+    }
+    placements
+}
+
+/// Whether the view shows a card left to draw and a free hand slot to draw it into.
+pub fn can_draw(view: &PlayerView) -> bool {
+    view.player_pile && view.player_hand.iter().any(|card| card.is_none())
+}
+
+/// Whether voting to flip would be accepted.
+pub fn can_vote_flip(view: &PlayerView, context: &StrategyContext) -> bool {
+    context.can_vote_flip && !view.flip_requested
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::game_logic::rank::Rank;
+use crate::game_logic::rules::RulesConfig;
 use crate::game_logic::suit::Suit;
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
@@ -10,19 +11,43 @@ pub struct Card {
 }
 
 impl Card {
+    /// The red and the black joker, dealt when the rules ask for them.
+    pub const JOKERS: [Card; 2] = [
+        Card {
+            rank: Rank::Joker,
+            suit: Suit::Red,
+        },
+        Card {
+            rank: Rank::Joker,
+            suit: Suit::Black,
+        },
+    ];
+
     pub fn new(rank: Rank, suit: Suit) -> Card {
         Card { rank, suit }
     }
 
-    pub fn is_adjacent_card(&self, other: &Card) -> bool {
+    pub fn is_joker(&self) -> bool {
+        self.rank == Rank::Joker
+    }
+
+    /// Whether this card can go on `other`: one rank above or below it, with
+    /// Ace and King next to each other when the rules wrap around. When the
+    /// rules deal jokers, a joker goes on anything and takes anything.
+    pub fn is_adjacent_card(&self, other: &Card, rules: &RulesConfig) -> bool {
+        if rules.jokers && (self.is_joker() || other.is_joker()) {
+            return true;
+        }
         match self.rank.value().abs_diff(other.rank.value()) {
-            1 | 12 => true,
+            1 => true,
+            12 => rules.wraparound,
             _ => false,
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
     use Rank::*;
@@ -30,34 +55,68 @@ mod tests {
 
     #[test]
     fn test_adjacent_cards() {
+        let rules = RulesConfig::default();
         assert_eq!(
-            Card::new(Ace, Spades).is_adjacent_card(&Card::new(King, Clubs)),
+            Card::new(Ace, Spades).is_adjacent_card(&Card::new(King, Clubs), &rules),
             true
         );
         assert_eq!(
-            Card::new(Two, Spades).is_adjacent_card(&Card::new(Three, Diamonds)),
+            Card::new(Two, Spades).is_adjacent_card(&Card::new(Three, Diamonds), &rules),
             true
         );
 
         assert_eq!(
-            Card::new(Queen, Hearts).is_adjacent_card(&Card::new(Jack, Hearts)),
+            Card::new(Queen, Hearts).is_adjacent_card(&Card::new(Jack, Hearts), &rules),
             true
         );
     }
 
     #[test]
     fn test_not_adjacent_cards() {
+        let rules = RulesConfig::default();
         assert_eq!(
-            Card::new(Ace, Spades).is_adjacent_card(&Card::new(Ace, Hearts)),
+            Card::new(Ace, Spades).is_adjacent_card(&Card::new(Ace, Hearts), &rules),
             false
         );
         assert_eq!(
-            Card::new(Two, Diamonds).is_adjacent_card(&Card::new(Four, Diamonds)),
+            Card::new(Two, Diamonds).is_adjacent_card(&Card::new(Four, Diamonds), &rules),
             false
         );
         assert_eq!(
-            Card::new(Seven, Clubs).is_adjacent_card(&Card::new(King, Clubs)),
+            Card::new(Seven, Clubs).is_adjacent_card(&Card::new(King, Clubs), &rules),
             false
         );
     }
+
+    #[test]
+    fn test_wraparound_is_optional() {
+        let rules = RulesConfig {
+            wraparound: false,
+            ..RulesConfig::default()
+        };
+        assert!(!Card::new(Ace, Spades).is_adjacent_card(&Card::new(King, Clubs), &rules));
+        assert!(Card::new(Ace, Spades).is_adjacent_card(&Card::new(Two, Clubs), &rules));
+    }
+
+    #[test]
+    fn test_jokers_go_anywhere() {
+        let rules = RulesConfig::default().with_jokers();
+        let [red, black] = Card::JOKERS;
+        for card in [Card::new(Seven, Clubs), Card::new(King, Hearts), black] {
+            assert!(red.is_adjacent_card(&card, &rules));
+            assert!(card.is_adjacent_card(&red, &rules));
+        }
+    }
+
+    #[test]
+    fn test_serde() {
+        let ace = serde_json::to_string(&Card::new(Ace, Spades)).unwrap();
+        assert_eq!(ace, "{\"rank\":\"Ace\",\"suit\":\"Spades\"}");
+        let joker = serde_json::to_string(&Card::JOKERS[0]).unwrap();
+        assert_eq!(joker, "{\"rank\":\"Joker\",\"suit\":\"Red\"}");
+        assert_eq!(
+            serde_json::from_str::<Card>(&joker).unwrap(),
+            Card::JOKERS[0]
+        );
+    }
 }
@@ -1,17 +1,32 @@
 mod card;
+pub use card::Card;
+
 mod piles;
+
 mod rank;
+pub use rank::Rank;
+
 mod suit;
+pub use suit::Suit;
 
 mod player;
-pub use player::Player;
+pub use player::{Player, MAX_PLAYERS};
 
 mod side;
 pub use side::Side;
 
+mod rules;
+pub use rules::{RulesConfig, RulesError, Variant, DECK_SIZE, MAX_HAND_SIZE};
+
 mod speedtable;
+#[cfg(test)]
+pub(crate) use speedtable::test_support;
+pub use speedtable::LegalActions;
 pub use speedtable::SpeedError;
 pub use speedtable::SpeedTable;
 
+mod table_event;
+pub use table_event::TableEvent;
+
 mod playerview;
-pub use playerview::PlayerView;
+pub use playerview::{PlayerView, SeatView, SpectatorView};
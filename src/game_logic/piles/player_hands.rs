@@ -3,25 +3,20 @@ use std::ops::{Index, IndexMut};
 use crate::game_logic::card::Card;
 use crate::game_logic::player::Player;
 
-#[derive(Debug)]
-pub struct PlayerHands(pub [Option<Card>; 4], pub [Option<Card>; 4]);
+/// One hand per seated player, indexed by `Player as usize`.
+#[derive(Clone, Debug)]
+pub struct PlayerHands(pub Vec<Vec<Option<Card>>>);
 
 impl Index<Player> for PlayerHands {
-    type Output = [Option<Card>; 4];
+    type Output = Vec<Option<Card>>;
 
     fn index(&self, player: Player) -> &Self::Output {
-        match player {
-            Player::PLAYER1 => &self.0,
-            Player::PLAYER2 => &self.1,
-        }
+        &self.0[player as usize]
     }
 }
 
 impl IndexMut<Player> for PlayerHands {
     fn index_mut(&mut self, player: Player) -> &mut Self::Output {
-        match player {
-            Player::PLAYER1 => &mut self.0,
-            Player::PLAYER2 => &mut self.1,
-        }
+        &mut self.0[player as usize]
     }
 }
@@ -3,25 +3,27 @@ use std::ops::{Index, IndexMut};
 use crate::game_logic::card::Card;
 use crate::game_logic::side::Side;
 
-#[derive(Debug)]
-pub struct SideIndexedPile(pub Vec<Card>, pub Vec<Card>);
+/// One pile per side of the table, indexed by `Side as usize`.
+#[derive(Clone, Debug)]
+pub struct SideIndexedPile(pub Vec<Vec<Card>>);
+
+impl SideIndexedPile {
+    /// The pile on `side`, if the table has one there.
+    pub fn get(&self, side: Side) -> Option<&Vec<Card>> {
+        self.0.get(side as usize)
+    }
+}
 
 impl Index<Side> for SideIndexedPile {
     type Output = Vec<Card>;
 
     fn index(&self, side: Side) -> &Self::Output {
-        match side {
-            Side::LEFT => &self.0,
-            Side::RIGHT => &self.1,
-        }
+        &self.0[side as usize]
     }
 }
 
 impl IndexMut<Side> for SideIndexedPile {
     fn index_mut(&mut self, side: Side) -> &mut Self::Output {
-        match side {
-            Side::LEFT => &mut self.0,
-            Side::RIGHT => &mut self.1,
-        }
+        &mut self.0[side as usize]
     }
 }
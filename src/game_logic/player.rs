@@ -1,14 +1,64 @@
-#[derive(Clone, Copy, Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+/// Most players a table can seat.
+pub const MAX_PLAYERS: usize = 4;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub enum Player {
     PLAYER1,
     PLAYER2,
+    PLAYER3,
+    PLAYER4,
 }
 
 impl Player {
-    pub fn opponent(&self) -> Player {
-        match self {
-            Player::PLAYER1 => Player::PLAYER2,
-            Player::PLAYER2 => Player::PLAYER1,
-        }
+    pub const ALL: [Player; MAX_PLAYERS] = [
+        Player::PLAYER1,
+        Player::PLAYER2,
+        Player::PLAYER3,
+        Player::PLAYER4,
+    ];
+
+    /// The seats of a table for `count` players, in order.
+    pub fn seated(count: usize) -> impl Iterator<Item = Player> {
+        Player::ALL.into_iter().take(count)
+    }
+
+    /// The partner sitting opposite in a four-player team game: `PLAYER1` with
+    /// `PLAYER3`, and `PLAYER2` with `PLAYER4`.
+    pub fn partner(self) -> Player {
+        Player::ALL[(self as usize + 2) % MAX_PLAYERS]
+    }
+
+    /// Everyone else at a table for `count` players, starting with the next seat
+    /// round the table.
+    pub fn opponents(self, count: usize) -> impl Iterator<Item = Player> {
+        let seat = self as usize;
+        (1..count).map(move |offset| Player::ALL[(seat + offset) % count])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opponents() {
+        let opponents = |player: Player, count| player.opponents(count).collect::<Vec<_>>();
+        assert_eq!(opponents(Player::PLAYER1, 2), [Player::PLAYER2]);
+        assert_eq!(opponents(Player::PLAYER2, 2), [Player::PLAYER1]);
+        assert_eq!(
+            opponents(Player::PLAYER3, 4),
+            [Player::PLAYER4, Player::PLAYER1, Player::PLAYER2]
+        );
+    }
+
+    #[test]
+    fn test_partner() {
+        assert_eq!(Player::PLAYER1.partner(), Player::PLAYER3);
+        assert_eq!(Player::PLAYER4.partner(), Player::PLAYER2);
+        assert!(Player::ALL
+            .iter()
+            .all(|player| player.partner().partner() == *player));
     }
 }
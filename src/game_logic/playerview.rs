@@ -1,16 +1,62 @@
 use serde::{Deserialize, Serialize};
 
-use super::card::Card;
+use super::{card::Card, player::Player, rules::RulesConfig};
 
 /*
  * A boolean field indicates whether a card should be rendered in the player view.
  * A false value means that a card does not exist for that specific spot.
+ *
+ * The `opponent_*` fields describe the next player round the table, which in a
+ * two-player game is the only opponent; `opponents` lists everyone else.
  */
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct PlayerView {
-    pub player_hand: [Option<Card>; 4],
-    pub active_cards: [Option<Card>; 2],
-    pub opponent_hand: [bool; 4],
+    pub player_hand: Vec<Option<Card>>,
+    pub player_pile: bool,
+    /// The top card of each active pile, indexed by `Side as usize`.
+    pub active_cards: Vec<Option<Card>>,
+    pub opponent_hand: Vec<bool>,
     pub opponent_pile: bool,
-    pub middle_piles: [bool; 2],
+    /// Whether each middle pile still has cards, indexed by `Side as usize`.
+    pub middle_piles: Vec<bool>,
+    pub flip_requested: bool,
+    pub opponent_flip_requested: bool,
+    /// Every other player, starting with the next one round the table, apart
+    /// from a team partner.
+    pub opponents: Vec<SeatView>,
+    /// Your partner in a team game, with their hand face up.
+    pub partner: Option<SeatView>,
+    /// What the table is played with, so clients know which cards can go where.
+    pub rules: RulesConfig,
+}
+
+/// What can be seen of one player's seat from elsewhere at the table.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SeatView {
+    pub player: Player,
+    /// Which hand slots hold a card.
+    pub hand: Vec<bool>,
+    /// The hand itself, only filled in when it is face up to the viewer.
+    pub cards: Option<Vec<Option<Card>>>,
+    pub pile: bool,
+    pub flip_requested: bool,
+}
+
+/*
+ * Neutral view of the table for spectators. Hand occupancy is always shown; the
+ * cards themselves are only filled in when the room reveals hands to spectators.
+ * The `player1_*` and `player2_*` fields repeat the first two of `players`.
+ */
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SpectatorView {
+    pub player1_hand: Vec<bool>,
+    pub player2_hand: Vec<bool>,
+    pub player1_cards: Option<Vec<Option<Card>>>,
+    pub player2_cards: Option<Vec<Option<Card>>>,
+    pub player1_pile: bool,
+    pub player2_pile: bool,
+    pub active_cards: Vec<Option<Card>>,
+    pub middle_piles: Vec<bool>,
+    /// Every seat at the table, in order.
+    pub players: Vec<SeatView>,
 }
@@ -16,9 +16,12 @@ pub enum Rank {
     Jack,
     Queen,
     King,
+    /// Only in decks dealt with jokers; see `Card::JOKERS`.
+    Joker,
 }
 
 impl Rank {
+    /// The thirteen ranks of each suit, leaving out the joker.
     pub fn iter() -> impl Iterator<Item = Rank> {
         [
             Ace, Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King,
@@ -27,6 +30,7 @@ impl Rank {
         .copied()
     }
 
+    /// Ace is 1 and King 13. A joker has no value of its own and counts as 0.
     pub fn value(&self) -> u8 {
         match self {
             Rank::Ace => 1,
@@ -42,6 +46,7 @@ impl Rank {
             Rank::Jack => 11,
             Rank::Queen => 12,
             Rank::King => 13,
+            Rank::Joker => 0,
         }
     }
 }
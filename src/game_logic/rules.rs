@@ -0,0 +1,368 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    card::Card,
+    player::{Player, MAX_PLAYERS},
+};
+
+/// Cards in the deck every table is dealt from, before any jokers are added.
+pub const DECK_SIZE: usize = 52;
+
+/// More hand slots than this make for an unwieldy view, and nobody plays that way.
+pub const MAX_HAND_SIZE: usize = 8;
+
+/// The sizes and rule switches a table is dealt and played with. Missing fields
+/// fall back to the variant's usual setup, and the variant to the house rules
+/// the game has always used.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(from = "PartialRules")]
+pub struct RulesConfig {
+    pub variant: Variant,
+    /// Players at the table, 2 to `MAX_PLAYERS`. Each player beyond the second
+    /// adds an active pile, and a middle pile to flip onto it.
+    pub players: usize,
+    /// Whether four players play as two teams of partners sitting opposite
+    /// each other (see `Player::partner`). A team wins once both partners have
+    /// run out of cards.
+    pub teams: bool,
+    /// Slots in each player's hand.
+    pub hand_size: usize,
+    /// Cards dealt to each player's draw pile.
+    pub player_pile_size: usize,
+    /// Cards dealt to each middle pile.
+    pub middle_pile_size: usize,
+    /// Whether an Ace and a King count as adjacent.
+    pub wraparound: bool,
+    /// Whether the two jokers are shuffled into the deck. A joker can be played
+    /// on any card and any card on a joker.
+    pub jokers: bool,
+    /// Whether the active piles are shuffled back in when a middle pile runs out
+    /// under the house rules. Without it, only the leftover middle cards are
+    /// shared out again, and the active piles come back only once there are too
+    /// few middle cards left to flip one onto each active pile. Classic tables
+    /// always restack the active piles.
+    pub reshuffle_active_piles: bool,
+}
+
+/// How the table is laid out and what a flip does.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Variant {
+    /// Hands start empty, the active piles start with the first flip, and a
+    /// middle pile running out reshuffles the middle.
+    #[default]
+    House,
+    /// Hands are dealt full and a card starts each active pile. The middle
+    /// piles are side stacks of replacement cards; once they run out, each
+    /// active pile is shuffled into a new side stack for its side.
+    Classic,
+}
+
+impl Default for RulesConfig {
+    fn default() -> Self {
+        RulesConfig::for_variant(Variant::House)
+    }
+}
+
+/// `RulesConfig` as sent by clients, who only need to name what they change.
+#[derive(Deserialize)]
+struct PartialRules {
+    variant: Option<Variant>,
+    players: Option<usize>,
+    teams: Option<bool>,
+    hand_size: Option<usize>,
+    player_pile_size: Option<usize>,
+    middle_pile_size: Option<usize>,
+    wraparound: Option<bool>,
+    jokers: Option<bool>,
+    reshuffle_active_piles: Option<bool>,
+}
+
+impl From<PartialRules> for RulesConfig {
+    fn from(partial: PartialRules) -> Self {
+        let mut base = RulesConfig::for_players(
+            partial.variant.unwrap_or_default(),
+            partial.players.unwrap_or(2),
+        );
+        if partial.jokers == Some(true) {
+            base = base.with_jokers();
+        }
+        RulesConfig {
+            variant: base.variant,
+            players: base.players,
+            teams: partial.teams.unwrap_or(base.teams),
+            hand_size: partial.hand_size.unwrap_or(base.hand_size),
+            player_pile_size: partial.player_pile_size.unwrap_or(base.player_pile_size),
+            middle_pile_size: partial.middle_pile_size.unwrap_or(base.middle_pile_size),
+            wraparound: partial.wraparound.unwrap_or(base.wraparound),
+            jokers: base.jokers,
+            reshuffle_active_piles: partial
+                .reshuffle_active_piles
+                .unwrap_or(base.reshuffle_active_piles),
+        }
+    }
+}
+
+/// Why a `RulesConfig` can't be dealt.
+#[derive(Debug, PartialEq)]
+pub enum RulesError {
+    Players(usize),
+    /// Teams need exactly four players.
+    Teams(usize),
+    HandSize(usize),
+    EmptyPlayerPile,
+    EmptyMiddlePile,
+    /// The piles need this many cards, more than the deck has.
+    NotEnoughCards(usize),
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesError::Players(players) => {
+                write!(f, "tables seat 2 to {MAX_PLAYERS} players, not {players}")
+            }
+            RulesError::Teams(players) => {
+                write!(f, "teams need 4 players, not {players}")
+            }
+            RulesError::HandSize(size) => {
+                write!(f, "hands need 1 to {MAX_HAND_SIZE} slots, not {size}")
+            }
+            RulesError::EmptyPlayerPile => write!(f, "player piles can't be empty"),
+            RulesError::EmptyMiddlePile => write!(f, "middle piles can't be empty"),
+            RulesError::NotEnoughCards(needed) => {
+                write!(f, "the piles need {needed} cards, more than the deck has")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RulesError {}
+
+impl RulesConfig {
+    /// The usual two-player setup for `variant`: 4-card hands, 19-card draw
+    /// piles and middle piles of 7 for the house rules; 5-card hands, 15-card
+    /// draw piles and side stacks of 5 for classic Speed. Both use the whole deck.
+    pub fn for_variant(variant: Variant) -> RulesConfig {
+        RulesConfig::for_players(variant, 2)
+    }
+
+    /// The usual setup for `variant` with `players` at the table, with the piles
+    /// shrunk so the deal still fits in one deck.
+    pub fn for_players(variant: Variant, players: usize) -> RulesConfig {
+        let (hand_size, player_pile_size, middle_pile_size) = match (variant, players) {
+            (Variant::House, 3) => (4, 12, 5),
+            (Variant::House, 4) => (4, 9, 4),
+            (Variant::House, _) => (4, 19, 7),
+            (Variant::Classic, 3) => (5, 8, 3),
+            (Variant::Classic, 4) => (5, 5, 2),
+            (Variant::Classic, _) => (5, 15, 5),
+        };
+        RulesConfig {
+            variant,
+            players,
+            teams: false,
+            hand_size,
+            player_pile_size,
+            middle_pile_size,
+            wraparound: true,
+            jokers: false,
+            reshuffle_active_piles: true,
+        }
+    }
+
+    /// These rules with the jokers shuffled in. Two players each get one more
+    /// card in their draw pile so the whole deck is still dealt; bigger tables
+    /// set aside whatever doesn't share out evenly, as without jokers.
+    pub fn with_jokers(self) -> RulesConfig {
+        let extra = if self.players == 2 { 1 } else { 0 };
+        RulesConfig {
+            jokers: true,
+            player_pile_size: self.player_pile_size + extra,
+            ..self
+        }
+    }
+
+    /// Cards in the deck the table is dealt from.
+    pub fn deck_size(&self) -> usize {
+        if self.jokers {
+            DECK_SIZE + Card::JOKERS.len()
+        } else {
+            DECK_SIZE
+        }
+    }
+
+    /// Active piles on the table, each with a middle pile to flip onto it:
+    /// two for two players, otherwise one per player.
+    pub fn active_piles(&self) -> usize {
+        self.players.max(2)
+    }
+
+    /// Who wins alongside `player` in a team game.
+    pub fn partner(&self, player: Player) -> Option<Player> {
+        self.teams.then(|| player.partner())
+    }
+
+    /// Cards the deal takes from the deck.
+    pub fn cards_dealt(&self) -> usize {
+        let mut per_player = self.player_pile_size;
+        let mut per_pile = self.middle_pile_size;
+        if self.variant == Variant::Classic {
+            // A full hand for each player and a starting card for each active pile.
+            per_player = per_player.saturating_add(self.hand_size);
+            per_pile = per_pile.saturating_add(1);
+        }
+        per_player
+            .saturating_mul(self.players)
+            .saturating_add(per_pile.saturating_mul(self.active_piles()))
+    }
+
+    /// Check that a table can be dealt with these rules from a single deck.
+    pub fn validate(&self) -> Result<(), RulesError> {
+        if !(2..=MAX_PLAYERS).contains(&self.players) {
+            return Err(RulesError::Players(self.players));
+        }
+        if self.teams && self.players != 4 {
+            return Err(RulesError::Teams(self.players));
+        }
+        if !(1..=MAX_HAND_SIZE).contains(&self.hand_size) {
+            return Err(RulesError::HandSize(self.hand_size));
+        }
+        if self.player_pile_size == 0 {
+            return Err(RulesError::EmptyPlayerPile);
+        }
+        if self.middle_pile_size == 0 {
+            return Err(RulesError::EmptyMiddlePile);
+        }
+        let needed = self.cards_dealt();
+        if needed > self.deck_size() {
+            return Err(RulesError::NotEnoughCards(needed));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(RulesConfig::default().validate(), Ok(()));
+
+        let rules = |hand_size, player_pile_size, middle_pile_size| RulesConfig {
+            hand_size,
+            player_pile_size,
+            middle_pile_size,
+            ..RulesConfig::default()
+        };
+        assert_eq!(rules(5, 20, 5).validate(), Ok(()));
+        assert_eq!(rules(0, 19, 7).validate(), Err(RulesError::HandSize(0)));
+        assert_eq!(rules(9, 19, 7).validate(), Err(RulesError::HandSize(9)));
+        assert_eq!(rules(4, 0, 7).validate(), Err(RulesError::EmptyPlayerPile));
+        assert_eq!(rules(4, 19, 0).validate(), Err(RulesError::EmptyMiddlePile));
+        assert_eq!(
+            rules(4, 20, 7).validate(),
+            Err(RulesError::NotEnoughCards(54))
+        );
+        assert!(rules(4, usize::MAX, 7).validate().is_err());
+
+        let players = |players| RulesConfig {
+            players,
+            ..RulesConfig::default()
+        };
+        assert_eq!(players(1).validate(), Err(RulesError::Players(1)));
+        assert_eq!(players(5).validate(), Err(RulesError::Players(5)));
+        assert_eq!(
+            players(3).validate(),
+            Err(RulesError::NotEnoughCards(3 * 19 + 3 * 7))
+        );
+
+        let teams = |players| RulesConfig {
+            teams: true,
+            ..RulesConfig::for_players(Variant::House, players)
+        };
+        assert_eq!(teams(4).validate(), Ok(()));
+        assert_eq!(teams(3).validate(), Err(RulesError::Teams(3)));
+        assert_eq!(teams(4).partner(Player::PLAYER2), Some(Player::PLAYER4));
+        assert_eq!(RulesConfig::default().partner(Player::PLAYER2), None);
+    }
+
+    #[test]
+    fn test_bigger_tables_fit_the_deck() {
+        for variant in [Variant::House, Variant::Classic] {
+            for players in 2..=MAX_PLAYERS {
+                let rules = RulesConfig::for_players(variant, players);
+                assert_eq!(rules.validate(), Ok(()), "{variant:?} for {players}");
+                assert!(rules.cards_dealt() > DECK_SIZE - players, "{rules:?}");
+            }
+        }
+
+        let rules: RulesConfig = serde_json::from_str("{\"players\":4}").unwrap();
+        assert_eq!(rules, RulesConfig::for_players(Variant::House, 4));
+        assert_eq!(rules.active_piles(), 4);
+    }
+
+    #[test]
+    fn test_classic_uses_the_whole_deck() {
+        let classic = RulesConfig::for_variant(Variant::Classic);
+        assert_eq!(classic.cards_dealt(), DECK_SIZE);
+        assert_eq!(RulesConfig::default().cards_dealt(), DECK_SIZE);
+
+        let bigger_hands = RulesConfig {
+            hand_size: 6,
+            ..classic
+        };
+        assert_eq!(bigger_hands.validate(), Err(RulesError::NotEnoughCards(54)));
+    }
+
+    #[test]
+    fn test_missing_fields_use_variant_setup() {
+        let rules: RulesConfig =
+            serde_json::from_str("{\"variant\":\"Classic\",\"wraparound\":false}").unwrap();
+        assert_eq!(
+            rules,
+            RulesConfig {
+                wraparound: false,
+                ..RulesConfig::for_variant(Variant::Classic)
+            }
+        );
+    }
+
+    #[test]
+    fn test_jokers_use_the_whole_deck() {
+        for variant in [Variant::House, Variant::Classic] {
+            let rules = RulesConfig::for_variant(variant).with_jokers();
+            assert_eq!(rules.cards_dealt(), DECK_SIZE + 2, "{variant:?}");
+            assert_eq!(rules.validate(), Ok(()));
+            assert_eq!(
+                RulesConfig {
+                    jokers: false,
+                    ..rules
+                }
+                .validate(),
+                Err(RulesError::NotEnoughCards(54))
+            );
+        }
+
+        let rules: RulesConfig = serde_json::from_str("{\"jokers\":true}").unwrap();
+        assert_eq!(rules, RulesConfig::default().with_jokers());
+        assert_eq!(rules.player_pile_size, 20);
+        let rules: RulesConfig =
+            serde_json::from_str("{\"jokers\":true,\"player_pile_size\":10}").unwrap();
+        assert_eq!(rules.player_pile_size, 10);
+    }
+
+    #[test]
+    fn test_missing_fields_use_house_rules() {
+        let rules: RulesConfig = serde_json::from_str("{\"wraparound\":false}").unwrap();
+        assert_eq!(
+            rules,
+            RulesConfig {
+                wraparound: false,
+                ..RulesConfig::default()
+            }
+        );
+    }
+}
@@ -1,7 +1,20 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+/// An active pile. Two players share `LEFT` and `RIGHT`; bigger tables add a
+/// pile per player.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Side {
     LEFT,
     RIGHT,
+    THIRD,
+    FOURTH,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [Side::LEFT, Side::RIGHT, Side::THIRD, Side::FOURTH];
+
+    /// The active piles of a table with `count` of them, in order.
+    pub fn piles(count: usize) -> impl Iterator<Item = Side> {
+        Side::ALL.into_iter().take(count)
+    }
 }
@@ -1,30 +1,58 @@
-use super::{player::Player, rank::Rank, suit::Suit, PlayerView};
+use super::{
+    player::Player, rank::Rank, suit::Suit, PlayerView, RulesConfig, RulesError, SeatView,
+    SpectatorView, TableEvent, Variant,
+};
 use crate::game_logic::card::Card;
 use crate::game_logic::piles::*;
 use crate::game_logic::side::Side;
 
-use rand::{seq::SliceRandom, thread_rng, RngCore};
+use rand::{seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SpeedTable {
     middle_piles: SideIndexedPile,
     active_piles: SideIndexedPile,
     player_hands: PlayerHands,
     player_piles: PlayerIndexedPile,
+    /// Which players currently want the middle cards flipped, indexed by `Player as usize`.
+    flip_votes: Vec<bool>,
+    /// Players who gave up their seat while the others play on, indexed by
+    /// `Player as usize`.
+    retired: Vec<bool>,
+    /// Every random step of the game draws from this, so a seed replays exactly.
+    rng: ChaCha8Rng,
+    seed: u64,
+    rules: RulesConfig,
 }
 
 use SpeedError as SE;
 
-/// Possible events that could arise apart from a simple card movement.
-/// Many of these should not be permitted from the client side.
+/// Every action currently open to a player, as accepted by `place_card`,
+/// `player_draw_card` and `vote_flip`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LegalActions {
+    /// Each `(hand_index, side)` pair that `place_card` would accept.
+    pub placements: Vec<(usize, Side)>,
+    pub can_draw: bool,
+    pub can_flip: bool,
+}
+
+/// Reasons a move can't be made. Many of these should not be permitted from the client side.
 #[derive(Debug, PartialEq)]
 pub enum SpeedError {
     NoCardToDraw,    // Client shouldn't allow
     HandAlreadyFull, // Client shouldn't allow
     NoCardToPlace,   // Client shouldn't allow
     NoCardToPlaceOn, // Client shouldn't allow
-    GameWon,
     NotAdjacentCard,
+    FlipAlreadyRequested,
+}
+
+impl Default for SpeedTable {
+    fn default() -> Self {
+        SpeedTable::new()
+    }
 }
 
 fn draw_cards(deck: &mut Vec<Card>, i: usize) -> Vec<Card> {
@@ -32,120 +60,449 @@ fn draw_cards(deck: &mut Vec<Card>, i: usize) -> Vec<Card> {
 }
 
 impl SpeedTable {
-    fn new_set_rng(rng: &mut dyn RngCore) -> SpeedTable {
+    /// Deal a table with the house rules whose shuffles, including later
+    /// reshuffles of the middle piles, are all determined by `seed`.
+    pub fn from_seed(seed: u64) -> SpeedTable {
+        SpeedTable::with_rules(RulesConfig::default(), seed).expect("the house rules are valid")
+    }
+
+    /// Deal a table for `rules` from `seed`. Cards the piles don't need are set
+    /// aside for the whole game.
+    pub fn with_rules(rules: RulesConfig, seed: u64) -> Result<SpeedTable, RulesError> {
+        rules.validate()?;
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
         let mut deck: Vec<Card> = Vec::new();
         for suit in Suit::iter() {
             for rank in Rank::iter() {
                 deck.push(Card { suit, rank });
             }
         }
+        if rules.jokers {
+            deck.extend(Card::JOKERS);
+        }
 
-        deck.shuffle(rng);
+        deck.shuffle(&mut rng);
 
-        let middle_piles = SideIndexedPile(draw_cards(&mut deck, 7), draw_cards(&mut deck, 7));
-        let player_piles = PlayerIndexedPile(draw_cards(&mut deck, 19), draw_cards(&mut deck, 19));
-        let active_piles = SideIndexedPile(Vec::new(), Vec::new());
-        let player_hands = PlayerHands([None; 4], [None; 4]);
+        let piles = rules.active_piles();
+        let middle_piles = SideIndexedPile(
+            (0..piles)
+                .map(|_| draw_cards(&mut deck, rules.middle_pile_size))
+                .collect(),
+        );
+        let player_piles = PlayerIndexedPile(
+            (0..rules.players)
+                .map(|_| draw_cards(&mut deck, rules.player_pile_size))
+                .collect(),
+        );
+        let (active_piles, player_hands) = match rules.variant {
+            Variant::House => (
+                SideIndexedPile(vec![Vec::new(); piles]),
+                PlayerHands(vec![vec![None; rules.hand_size]; rules.players]),
+            ),
+            Variant::Classic => {
+                let player_hands = (0..rules.players)
+                    .map(|_| {
+                        draw_cards(&mut deck, rules.hand_size)
+                            .into_iter()
+                            .map(Some)
+                            .collect()
+                    })
+                    .collect();
+                let active_piles = (0..piles).map(|_| draw_cards(&mut deck, 1)).collect();
+                (SideIndexedPile(active_piles), PlayerHands(player_hands))
+            }
+        };
 
-        SpeedTable {
+        Ok(SpeedTable {
             middle_piles,
             active_piles,
             player_piles,
             player_hands,
-        }
+            flip_votes: vec![false; rules.players],
+            retired: vec![false; rules.players],
+            rng,
+            seed,
+            rules,
+        })
     }
 
     pub fn new() -> SpeedTable {
-        SpeedTable::new_set_rng(&mut thread_rng())
+        SpeedTable::from_seed(thread_rng().gen())
     }
 
-    /// Move the top cards on the middle piles onto the active piles.
-    /// This is done on request by both players when they think they have no more cards to play.
-    pub fn flip_middle_cards(&mut self) -> Result<(), SpeedError> {
-        if self.middle_piles[Side::LEFT].last().is_none()
-            || self.middle_piles[Side::RIGHT].last().is_none()
+    /// The seed this table was dealt from; `SpeedTable::from_seed` with it and the
+    /// same moves reproduces the game exactly.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rules(&self) -> &RulesConfig {
+        &self.rules
+    }
+
+    /// Everyone seated at the table, in order.
+    pub fn players(&self) -> impl Iterator<Item = Player> {
+        Player::seated(self.rules.players)
+    }
+
+    fn sides(&self) -> impl Iterator<Item = Side> {
+        Side::piles(self.rules.active_piles())
+    }
+
+    /// Record that `player` is stuck and wants the middle cards flipped. The flip
+    /// only happens once every player has asked for it, apart from players in a
+    /// team game who are out of cards and waiting on their partner, and players
+    /// who have retired.
+    pub fn vote_flip(&mut self, player: Player) -> Result<Vec<TableEvent>, SpeedError> {
+        if self.flip_votes[player as usize] {
+            return Err(SE::FlipAlreadyRequested);
+        }
+        self.flip_votes[player as usize] = true;
+
+        let mut events = vec![TableEvent::FlipVoted { player }];
+        events.append(&mut self.flip_if_everyone_voted()?);
+        Ok(events)
+    }
+
+    /// Take `player` out of the game while everyone else plays on. Their cards
+    /// stay where they are, and they no longer count for flip votes or
+    /// stalemates, so a flip everyone left has already asked for happens now.
+    pub fn retire(&mut self, player: Player) -> Result<Vec<TableEvent>, SpeedError> {
+        if self.has_retired(player) {
+            return Ok(Vec::new());
+        }
+        self.retired[player as usize] = true;
+
+        let mut events = vec![TableEvent::PlayerRetired { player }];
+        events.append(&mut self.flip_if_everyone_voted()?);
+        Ok(events)
+    }
+
+    pub fn has_retired(&self, player: Player) -> bool {
+        self.retired[player as usize]
+    }
+
+    /// Everyone at the table who hasn't retired, in order.
+    pub fn remaining_players(&self) -> impl Iterator<Item = Player> + '_ {
+        self.players().filter(|&player| !self.has_retired(player))
+    }
+
+    fn flip_if_everyone_voted(&mut self) -> Result<Vec<TableEvent>, SpeedError> {
+        if self
+            .remaining_players()
+            .all(|player| self.has_voted_flip(player) || self.cards_left(player) == 0)
         {
-            let mut combined_pile = Vec::new();
-            combined_pile.append(&mut self.middle_piles[Side::LEFT]);
-            combined_pile.append(&mut self.middle_piles[Side::RIGHT]);
-            combined_pile.append(&mut self.active_piles[Side::LEFT]);
-            combined_pile.append(&mut self.active_piles[Side::RIGHT]);
-            combined_pile.shuffle(&mut thread_rng());
-            self.middle_piles[Side::LEFT] =
-                combined_pile.drain(0..combined_pile.len() / 2).collect();
-            self.middle_piles[Side::RIGHT].append(&mut combined_pile);
+            self.flip_middle_cards()
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    pub fn has_voted_flip(&self, player: Player) -> bool {
+        self.flip_votes[player as usize]
+    }
+
+    /// Move the top cards on the middle piles onto the active piles.
+    /// This is done on request by every player when they think they have no more cards to play.
+    pub fn flip_middle_cards(&mut self) -> Result<Vec<TableEvent>, SpeedError> {
+        let mut events = Vec::new();
+        if self.middle_piles.0.iter().any(|pile| pile.is_empty()) {
+            match self.rules.variant {
+                Variant::House => self.reshuffle_middle(),
+                Variant::Classic => self.restack_sides(),
+            }
+            events.push(TableEvent::MiddleReshuffled {
+                piles: self.middle_piles.0.clone(),
+            });
+        }
+        let mut cards = Vec::new();
+        for side in self.sides() {
+            let card = self.middle_piles[side].pop().unwrap();
+            self.active_piles[side].push(card);
+            cards.push(card);
         }
-        self.active_piles[Side::LEFT].push(self.middle_piles[Side::LEFT].pop().unwrap());
-        self.active_piles[Side::RIGHT].push(self.middle_piles[Side::RIGHT].pop().unwrap());
+        self.clear_flip_votes();
+        events.push(TableEvent::MiddleFlipped { cards });
 
-        Ok(())
+        Ok(events)
+    }
+
+    fn clear_flip_votes(&mut self) {
+        self.flip_votes.fill(false);
+    }
+
+    /// Shuffle the middle piles together and split them evenly again, with any
+    /// odd cards going to the last piles.
+    fn reshuffle_middle(&mut self) {
+        let mut combined_pile: Vec<Card> = self
+            .middle_piles
+            .0
+            .iter_mut()
+            .flat_map(std::mem::take)
+            .collect();
+        // Every side needs a card, so the active piles come back when the
+        // leftovers can't cover that even if the rules keep them out.
+        let piles = self.rules.active_piles();
+        if self.rules.reshuffle_active_piles || combined_pile.len() < piles {
+            for pile in &mut self.active_piles.0 {
+                combined_pile.append(pile);
+            }
+        }
+        combined_pile.shuffle(&mut self.rng);
+        for (index, pile) in self.middle_piles.0.iter_mut().enumerate() {
+            let share = combined_pile.len() / (piles - index);
+            *pile = combined_pile.drain(0..share).collect();
+        }
+    }
+
+    /// Turn each active pile into a fresh side stack for its own side, under
+    /// whatever that stack had left. Every classic active pile holds at least
+    /// its starting card, so every stack comes out with something to flip.
+    fn restack_sides(&mut self) {
+        for side in self.sides().collect::<Vec<_>>() {
+            let mut stack = std::mem::take(&mut self.active_piles[side]);
+            stack.shuffle(&mut self.rng);
+            stack.append(&mut self.middle_piles[side]);
+            self.middle_piles[side] = stack;
+        }
     }
 
     fn get_first_empty_hand_idx(&self, player: Player) -> Option<usize> {
         self.player_hands[player].iter().position(|x| x.is_none())
     }
 
+    /// Whether `player` and, in a team game, their partner are out of cards.
     fn check_for_win(&self, player: Player) -> bool {
-        self.player_piles[player].len() == 0
-            && self.player_hands[player].iter().all(|x| x.is_none())
+        std::iter::once(player)
+            .chain(self.rules.partner(player))
+            .all(|member| self.cards_left(member) == 0)
     }
 
-    pub fn player_draw_card(&mut self, player: Player) -> Result<(), SpeedError> {
+    pub fn player_draw_card(&mut self, player: Player) -> Result<Vec<TableEvent>, SpeedError> {
         let first_empty_index = self
             .get_first_empty_hand_idx(player)
             .ok_or(SE::HandAlreadyFull)?;
 
         let card_to_draw = self.player_piles[player].pop().ok_or(SE::NoCardToDraw)?;
         self.player_hands[player][first_empty_index] = Some(card_to_draw);
-        Ok(())
+        Ok(vec![TableEvent::CardDrawn {
+            player,
+            hand_index: first_empty_index,
+            card: card_to_draw,
+        }])
     }
 
+    /// Place a hand card on an active pile. The events end with `PlayerWon` if
+    /// that was the last card the player, or their team, had left.
     pub fn place_card(
         &mut self,
         player: Player,
         side: Side,
         hand_index: usize,
-    ) -> Result<(), SpeedError> {
-        let card_to_place = self.player_hands[player][hand_index].ok_or(SE::NoCardToPlace)?;
-        let card_place_on = self.active_piles[side].last().ok_or(SE::NoCardToPlaceOn)?;
+    ) -> Result<Vec<TableEvent>, SpeedError> {
+        let card_to_place = self.player_hands[player]
+            .get(hand_index)
+            .copied()
+            .flatten()
+            .ok_or(SE::NoCardToPlace)?;
+        let card_place_on = self
+            .active_piles
+            .get(side)
+            .and_then(|pile| pile.last())
+            .ok_or(SE::NoCardToPlaceOn)?;
 
-        if card_to_place.is_adjacent_card(card_place_on) {
-            self.active_piles[side].push(card_to_place);
-            self.player_hands[player][hand_index] = None;
+        if !card_to_place.is_adjacent_card(card_place_on, &self.rules) {
+            return Err(SE::NotAdjacentCard);
+        }
 
-            if self.check_for_win(player) {
-                return Err(SE::GameWon);
-            };
+        self.active_piles[side].push(card_to_place);
+        self.player_hands[player][hand_index] = None;
+        // Whoever asked for a flip was evidently not stuck after all.
+        self.clear_flip_votes();
 
-            return Ok(());
-        } else {
-            return Err(SE::NotAdjacentCard);
+        let mut events = vec![TableEvent::CardPlaced {
+            player,
+            hand_index,
+            side,
+            card: card_to_place,
+        }];
+        if self.check_for_win(player) {
+            events.push(TableEvent::PlayerWon { player });
+        }
+        Ok(events)
+    }
+
+    pub fn legal_actions(&self, player: Player) -> LegalActions {
+        LegalActions {
+            placements: self.legal_placements(player),
+            can_draw: self.can_draw(player),
+            can_flip: !self.has_voted_flip(player),
+        }
+    }
+
+    fn legal_placements(&self, player: Player) -> Vec<(usize, Side)> {
+        let mut placements = Vec::new();
+        for (hand_index, card) in self.player_hands[player].iter().enumerate() {
+            let Some(card) = card else { continue };
+            for side in self.sides() {
+                if self.active_piles[side]
+                    .last()
+                    .is_some_and(|top_card| card.is_adjacent_card(top_card, &self.rules))
+                {
+                    placements.push((hand_index, side));
+                }
+            }
         }
+        placements
+    }
+
+    fn can_draw(&self, player: Player) -> bool {
+        self.get_first_empty_hand_idx(player).is_some() && !self.player_piles[player].is_empty()
+    }
+
+    /// Whether `player` can do anything other than wait for a flip: draw into an
+    /// empty hand slot, or place a hand card on one of the active piles.
+    pub fn has_legal_play(&self, player: Player) -> bool {
+        self.can_draw(player) || !self.legal_placements(player).is_empty()
+    }
+
+    /// No player still in the game can move, so the only way forward is
+    /// flipping the middle cards.
+    pub fn is_stalemate(&self) -> bool {
+        self.remaining_players()
+            .all(|player| !self.has_legal_play(player))
+    }
+
+    /// Cards `player` still has to get rid of, in hand and pile.
+    pub fn cards_left(&self, player: Player) -> usize {
+        let in_hand = self.player_hands[player].iter().flatten().count();
+        in_hand + self.player_piles[player].len()
     }
 
     pub fn get_player_view(&self, player: Player) -> PlayerView {
-        let opponent_hand = self.player_hands[player.opponent()].map(|x| x.is_some());
+        let partner = self.rules.partner(player);
+        let opponents: Vec<SeatView> = player
+            .opponents(self.rules.players)
+            .filter(|&opponent| Some(opponent) != partner)
+            .map(|opponent| self.seat_view(opponent, false))
+            .collect();
+        let next = &opponents[0];
         PlayerView {
-            player_hand: self.player_hands[player],
-            active_cards: [
-                self.active_piles[Side::LEFT].last().copied(),
-                self.active_piles[Side::RIGHT].last().copied(),
-            ],
-            opponent_hand,
-            opponent_pile: self.player_piles[player.opponent()].len() != 0,
-            middle_piles: [
-                !self.middle_piles[Side::LEFT].is_empty(),
-                !self.middle_piles[Side::RIGHT].is_empty(),
-            ],
+            player_hand: self.player_hands[player].clone(),
+            player_pile: !self.player_piles[player].is_empty(),
+            active_cards: self.active_cards(),
+            opponent_hand: next.hand.clone(),
+            opponent_pile: next.pile,
+            flip_requested: self.has_voted_flip(player),
+            opponent_flip_requested: next.flip_requested,
+            middle_piles: self.middle_piles(),
+            opponents,
+            partner: partner.map(|partner| self.seat_view(partner, true)),
+            rules: self.rules,
+        }
+    }
+
+    pub fn get_spectator_view(&self, reveal_hands: bool) -> SpectatorView {
+        let players: Vec<SeatView> = self
+            .players()
+            .map(|player| self.seat_view(player, reveal_hands))
+            .collect();
+        SpectatorView {
+            player1_hand: players[0].hand.clone(),
+            player2_hand: players[1].hand.clone(),
+            player1_cards: players[0].cards.clone(),
+            player2_cards: players[1].cards.clone(),
+            player1_pile: players[0].pile,
+            player2_pile: players[1].pile,
+            active_cards: self.active_cards(),
+            middle_piles: self.middle_piles(),
+            players,
         }
     }
+
+    fn seat_view(&self, player: Player, face_up: bool) -> SeatView {
+        let hand = &self.player_hands[player];
+        SeatView {
+            player,
+            hand: hand.iter().map(|x| x.is_some()).collect(),
+            cards: face_up.then(|| hand.clone()),
+            pile: !self.player_piles[player].is_empty(),
+            flip_requested: self.has_voted_flip(player),
+        }
+    }
+
+    fn active_cards(&self) -> Vec<Option<Card>> {
+        self.active_piles
+            .0
+            .iter()
+            .map(|pile| pile.last().copied())
+            .collect()
+    }
+
+    fn middle_piles(&self) -> Vec<bool> {
+        self.middle_piles
+            .0
+            .iter()
+            .map(|pile| !pile.is_empty())
+            .collect()
+    }
 }
 
+/// Tables for tests here and elsewhere in the crate.
 #[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+    use crate::PlayerAction;
+
+    /// A table dealt from `seed` under `rules`, which must be valid.
+    pub fn table_with(rules: RulesConfig, seed: u64) -> SpeedTable {
+        SpeedTable::with_rules(rules, seed).unwrap()
+    }
+
+    /// Play the game out and return the winner, see `play_out_with`.
+    pub fn play_out(table: &mut SpeedTable) -> Player {
+        play_out_with(table, |_, _, _| {})
+    }
+
+    /// Give each seat a turn in order until somebody wins: place the first
+    /// playable card, or else draw, or else vote to flip. `each_turn` sees the
+    /// table before every turn along with the move about to be made, if any.
+    pub fn play_out_with(
+        table: &mut SpeedTable,
+        mut each_turn: impl FnMut(&SpeedTable, Player, Option<PlayerAction>),
+    ) -> Player {
+        let players: Vec<Player> = table.players().collect();
+        for turn in 0..20_000 {
+            let player = players[turn % players.len()];
+            let legal = table.legal_actions(player);
+            let action = if let Some(&(hand_index, side)) = legal.placements.first() {
+                Some(PlayerAction::PlaceCard(hand_index, side))
+            } else if legal.can_draw {
+                Some(PlayerAction::DrawCard)
+            } else if legal.can_flip {
+                Some(PlayerAction::Flip)
+            } else {
+                None
+            };
+            each_turn(table, player, action);
+            let Some(action) = action else { continue };
+            let events = action.apply(table, player).unwrap();
+            if let Some(winner) = events.iter().find_map(|event| match event {
+                TableEvent::PlayerWon { player } => Some(*player),
+                _ => None,
+            }) {
+                return winner;
+            }
+        }
+        panic!("the game dealt from seed {} never finished", table.seed());
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison, unused_must_use)]
 mod tests {
+    use super::test_support::{play_out, play_out_with, table_with};
     use super::*;
-    use rand::prelude::*;
-    use rand_chacha::ChaCha8Rng;
 
     #[test]
     fn test_table_init() {
@@ -224,7 +581,7 @@ mod tests {
             }),
         ];
 
-        table.player_hands = PlayerHands(player_hand.clone(), player_hand.clone());
+        table.player_hands = PlayerHands(vec![player_hand.to_vec(); 2]);
 
         let card_to_draw_player1 = table.player_piles[Player::PLAYER1][18];
         let card_to_draw_player2 = table.player_piles[Player::PLAYER2][18];
@@ -244,11 +601,10 @@ mod tests {
 
     #[test]
     fn test_place_card() {
-        let mut rng = ChaCha8Rng::seed_from_u64(0);
-        let mut table = SpeedTable::new_set_rng(&mut rng);
+        let mut table = SpeedTable::from_seed(0);
 
-        while let Ok(()) = table.player_draw_card(Player::PLAYER1) {}
-        while let Ok(()) = table.player_draw_card(Player::PLAYER2) {}
+        while table.player_draw_card(Player::PLAYER1).is_ok() {}
+        while table.player_draw_card(Player::PLAYER2).is_ok() {}
 
         table.flip_middle_cards();
 
@@ -256,7 +612,25 @@ mod tests {
             table.place_card(Player::PLAYER1, Side::RIGHT, 1),
             Err(SE::NotAdjacentCard)
         );
-        assert_eq!(table.place_card(Player::PLAYER1, Side::LEFT, 1), Ok(()));
+        let card = table.player_hands[Player::PLAYER1][1].unwrap();
+        assert_eq!(
+            table.place_card(Player::PLAYER1, Side::LEFT, 1),
+            Ok(vec![TableEvent::CardPlaced {
+                player: Player::PLAYER1,
+                hand_index: 1,
+                side: Side::LEFT,
+                card,
+            }])
+        );
+        assert_eq!(table.active_piles[Side::LEFT].last(), Some(&card));
+        assert_eq!(
+            table.place_card(Player::PLAYER1, Side::LEFT, 1),
+            Err(SE::NoCardToPlace)
+        );
+        assert_eq!(
+            table.place_card(Player::PLAYER1, Side::LEFT, 4),
+            Err(SE::NoCardToPlace)
+        );
     }
 
     #[test]
@@ -279,15 +653,14 @@ mod tests {
 
     #[test]
     fn test_middle_reshuffle_unequal() {
-        let mut rng = ChaCha8Rng::seed_from_u64(0);
-        let mut table = SpeedTable::new_set_rng(&mut rng);
+        let mut table = SpeedTable::from_seed(0);
 
-        while let Ok(()) = table.player_draw_card(Player::PLAYER1) {}
-        while let Ok(()) = table.player_draw_card(Player::PLAYER2) {}
+        while table.player_draw_card(Player::PLAYER1).is_ok() {}
+        while table.player_draw_card(Player::PLAYER2).is_ok() {}
 
         table.flip_middle_cards();
 
-        assert_eq!(table.place_card(Player::PLAYER1, Side::LEFT, 1), Ok(()));
+        assert!(table.place_card(Player::PLAYER1, Side::LEFT, 1).is_ok());
 
         for _ in 0..6 {
             table.flip_middle_cards();
@@ -314,4 +687,588 @@ mod tests {
         assert_eq!(table.middle_piles[Side::LEFT].len(), 6);
         assert_eq!(table.middle_piles[Side::RIGHT].len(), 7);
     }
+
+    #[test]
+    fn test_flip_events_describe_the_piles() {
+        let mut table = SpeedTable::from_seed(3);
+        for _ in 0..7 {
+            let events = table.flip_middle_cards().unwrap();
+            assert!(matches!(
+                events.as_slice(),
+                [TableEvent::MiddleFlipped { .. }]
+            ));
+        }
+
+        let events = table.flip_middle_cards().unwrap();
+        let [TableEvent::MiddleReshuffled { piles }, TableEvent::MiddleFlipped { cards }] =
+            events.as_slice()
+        else {
+            panic!("expected a reshuffle and a flip, got {events:?}");
+        };
+        let [left, right] = piles.as_slice() else {
+            panic!("expected two middle piles, got {piles:?}");
+        };
+        let [flipped_left, flipped_right] = cards.as_slice() else {
+            panic!("expected two flipped cards, got {cards:?}");
+        };
+
+        assert_eq!(left.last(), Some(flipped_left));
+        assert_eq!(right.last(), Some(flipped_right));
+        assert_eq!(table.middle_piles[Side::LEFT], left[..left.len() - 1]);
+        assert_eq!(table.middle_piles[Side::RIGHT], right[..right.len() - 1]);
+        assert_eq!(table.active_piles[Side::LEFT], [*flipped_left]);
+        assert_eq!(table.active_piles[Side::RIGHT], [*flipped_right]);
+    }
+
+    #[test]
+    fn test_spectator_view() {
+        let mut table = SpeedTable::new();
+        let _ = table.player_draw_card(Player::PLAYER1);
+        let drawn_card = table.player_hands[Player::PLAYER1][0];
+
+        let hidden = table.get_spectator_view(false);
+        assert_eq!(hidden.player1_hand, [true, false, false, false]);
+        assert_eq!(hidden.player2_hand, [false; 4]);
+        assert_eq!(hidden.player1_cards, None);
+        assert_eq!(hidden.player2_cards, None);
+
+        let revealed = table.get_spectator_view(true);
+        assert_eq!(revealed.player1_cards.unwrap()[0], drawn_card);
+        assert_eq!(revealed.player2_cards, Some(vec![None; 4]));
+    }
+
+    #[test]
+    fn test_vote_flip() {
+        let mut table = SpeedTable::from_seed(0);
+
+        while table.player_draw_card(Player::PLAYER1).is_ok() {}
+
+        assert_eq!(
+            table.vote_flip(Player::PLAYER1),
+            Ok(vec![TableEvent::FlipVoted {
+                player: Player::PLAYER1
+            }])
+        );
+        assert_eq!(
+            table.vote_flip(Player::PLAYER1),
+            Err(SE::FlipAlreadyRequested)
+        );
+        assert!(table.active_piles[Side::LEFT].is_empty());
+        assert!(
+            table
+                .get_player_view(Player::PLAYER2)
+                .opponent_flip_requested
+        );
+
+        let events = table.vote_flip(Player::PLAYER2).unwrap();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                TableEvent::FlipVoted {
+                    player: Player::PLAYER2
+                },
+                TableEvent::MiddleFlipped { .. }
+            ]
+        ));
+        assert_eq!(table.active_piles[Side::LEFT].len(), 1);
+        assert!(!table.has_voted_flip(Player::PLAYER1));
+        assert!(!table.has_voted_flip(Player::PLAYER2));
+
+        assert!(table.vote_flip(Player::PLAYER2).is_ok());
+        assert!(table.place_card(Player::PLAYER1, Side::LEFT, 1).is_ok());
+        assert!(!table.has_voted_flip(Player::PLAYER2));
+    }
+
+    #[test]
+    fn test_stalemate() {
+        use Rank::*;
+        use Suit::*;
+
+        let mut table = SpeedTable::new();
+        // Nothing to place on before the first flip, but both players can draw.
+        assert!(!table.is_stalemate());
+
+        table.player_piles = PlayerIndexedPile(vec![Vec::new(); 2]);
+        assert!(table.is_stalemate());
+
+        table.active_piles = SideIndexedPile(vec![
+            vec![Card::new(Five, Clubs)],
+            vec![Card::new(Nine, Hearts)],
+        ]);
+        table.player_hands = PlayerHands(vec![
+            vec![Some(Card::new(Two, Spades)), None, None, None],
+            vec![None, Some(Card::new(Queen, Diamonds)), None, None],
+        ]);
+        assert!(table.is_stalemate());
+
+        table.player_hands[Player::PLAYER2][2] = Some(Card::new(Ten, Spades));
+        assert!(!table.has_legal_play(Player::PLAYER1));
+        assert!(table.has_legal_play(Player::PLAYER2));
+        assert!(!table.is_stalemate());
+    }
+
+    /// Check `legal_actions` against what the table actually accepts, move by move,
+    /// over a few seeded games.
+    #[test]
+    fn test_legal_actions_match_moves() {
+        for seed in 0..20 {
+            let mut table = SpeedTable::from_seed(seed);
+            play_out_with(&mut table, |table, player, _| {
+                let legal = table.legal_actions(player);
+                for hand_index in 0..5 {
+                    for side in [Side::LEFT, Side::RIGHT] {
+                        let result = table.clone().place_card(player, side, hand_index);
+                        assert_eq!(
+                            result.is_ok(),
+                            legal.placements.contains(&(hand_index, side)),
+                            "seed {seed}: placing {hand_index} on {side:?}"
+                        );
+                    }
+                }
+                assert_eq!(
+                    table.clone().player_draw_card(player).is_ok(),
+                    legal.can_draw
+                );
+                // A second vote from a stuck player is refused until the other one votes.
+                assert_eq!(table.clone().vote_flip(player).is_ok(), legal.can_flip);
+            });
+        }
+    }
+
+    #[test]
+    fn test_deal_follows_rules() {
+        let rules = RulesConfig {
+            hand_size: 5,
+            player_pile_size: 15,
+            middle_pile_size: 3,
+            ..RulesConfig::default()
+        };
+        let table = table_with(rules, 0);
+        assert_eq!(table.player_piles[Player::PLAYER2].len(), 15);
+        assert_eq!(table.middle_piles[Side::RIGHT].len(), 3);
+        assert_eq!(table.player_hands[Player::PLAYER1], vec![None; 5]);
+        assert_eq!(
+            table.get_player_view(Player::PLAYER1).opponent_hand.len(),
+            5
+        );
+
+        let too_big = RulesConfig {
+            middle_pile_size: 8,
+            ..RulesConfig::default()
+        };
+        assert_eq!(
+            SpeedTable::with_rules(too_big, 0).unwrap_err(),
+            RulesError::NotEnoughCards(54)
+        );
+    }
+
+    #[test]
+    fn test_reshuffle_without_active_piles() {
+        let rules = RulesConfig {
+            reshuffle_active_piles: false,
+            ..RulesConfig::default()
+        };
+        let mut table = table_with(rules, 0);
+        // Leave the left middle pile empty and three cards on the right.
+        let moved: Vec<Card> = table.middle_piles[Side::LEFT].drain(..).collect();
+        table.middle_piles[Side::RIGHT].truncate(3);
+        table.active_piles[Side::LEFT] = moved;
+
+        table.flip_middle_cards().unwrap();
+        assert_eq!(table.active_piles[Side::LEFT].len(), 8);
+        assert_eq!(table.active_piles[Side::RIGHT].len(), 1);
+        assert_eq!(table.middle_piles[Side::LEFT].len(), 0);
+        assert_eq!(table.middle_piles[Side::RIGHT].len(), 1);
+
+        // One card between them can't cover both sides, so the active piles come back.
+        table.flip_middle_cards().unwrap();
+        assert_eq!(table.active_piles[Side::LEFT].len(), 1);
+        assert_eq!(table.active_piles[Side::RIGHT].len(), 1);
+        assert_eq!(
+            table.middle_piles[Side::LEFT].len() + table.middle_piles[Side::RIGHT].len(),
+            8
+        );
+    }
+
+    #[test]
+    fn test_classic_deal() {
+        let table = table_with(RulesConfig::for_variant(Variant::Classic), 0);
+
+        for player in [Player::PLAYER1, Player::PLAYER2] {
+            assert_eq!(table.player_piles[player].len(), 15);
+            assert_eq!(table.player_hands[player].len(), 5);
+            assert!(table.player_hands[player].iter().all(|card| card.is_some()));
+        }
+        for side in [Side::LEFT, Side::RIGHT] {
+            assert_eq!(table.middle_piles[side].len(), 5);
+            assert_eq!(table.active_piles[side].len(), 1);
+        }
+
+        // Every card of the deck is somewhere on the table, once.
+        let cards: Vec<Card> = [Player::PLAYER1, Player::PLAYER2]
+            .into_iter()
+            .flat_map(|player| {
+                let hand = table.player_hands[player].iter().flatten();
+                hand.chain(&table.player_piles[player]).copied()
+            })
+            .chain([Side::LEFT, Side::RIGHT].into_iter().flat_map(|side| {
+                let middle = table.middle_piles[side].iter();
+                middle.chain(&table.active_piles[side]).copied()
+            }))
+            .collect();
+        assert_eq!(cards.len(), 52);
+        for (index, card) in cards.iter().enumerate() {
+            assert!(!cards[index + 1..].contains(card));
+        }
+
+        // The starting cards are already face up, so players can play straight away.
+        let view = table.get_player_view(Player::PLAYER1);
+        assert!(view.active_cards.iter().all(|card| card.is_some()));
+        assert!(!view.player_hand.contains(&None));
+    }
+
+    #[test]
+    fn test_classic_flip_and_restack() {
+        let mut table = table_with(RulesConfig::for_variant(Variant::Classic), 1);
+        let left_start = table.active_piles[Side::LEFT][0];
+        let next_left = *table.middle_piles[Side::LEFT].last().unwrap();
+
+        let events = table.flip_middle_cards().unwrap();
+        assert!(matches!(
+            events.as_slice(),
+            [TableEvent::MiddleFlipped { .. }]
+        ));
+        assert_eq!(table.active_piles[Side::LEFT], [left_start, next_left]);
+        assert_eq!(table.middle_piles[Side::LEFT].len(), 4);
+
+        for _ in 0..4 {
+            table.flip_middle_cards().unwrap();
+        }
+        assert!(table.middle_piles[Side::LEFT].is_empty());
+        let played_left = table.active_piles[Side::LEFT].clone();
+
+        // The stacks have run out, so each active pile becomes its side's new stack.
+        let events = table.flip_middle_cards().unwrap();
+        let [TableEvent::MiddleReshuffled { piles }, TableEvent::MiddleFlipped { .. }] =
+            events.as_slice()
+        else {
+            panic!("expected a restack and a flip, got {events:?}");
+        };
+        let [left, right] = piles.as_slice() else {
+            panic!("expected two side stacks, got {piles:?}");
+        };
+        assert_eq!(left.len(), 6);
+        assert_eq!(right.len(), 6);
+        assert!(left.iter().all(|card| played_left.contains(card)));
+        assert_eq!(table.active_piles[Side::LEFT].len(), 1);
+        assert_eq!(table.middle_piles[Side::LEFT].len(), 5);
+        assert_eq!(table.middle_piles[Side::RIGHT].len(), 5);
+    }
+
+    #[test]
+    fn test_classic_games_finish() {
+        for seed in 0..10 {
+            play_out(&mut table_with(
+                RulesConfig::for_variant(Variant::Classic),
+                seed,
+            ));
+        }
+    }
+
+    #[test]
+    fn test_bigger_table_deal() {
+        for players in 3..=4 {
+            let rules = RulesConfig::for_players(Variant::House, players);
+            let table = table_with(rules, 0);
+            assert_eq!(table.players().count(), players);
+            assert_eq!(table.active_piles.0.len(), players);
+            assert_eq!(table.middle_piles.0.len(), players);
+            for player in table.players() {
+                assert_eq!(table.cards_left(player), rules.player_pile_size);
+            }
+
+            let view = table.get_player_view(Player::PLAYER2);
+            assert_eq!(view.active_cards, vec![None; players]);
+            assert_eq!(view.middle_piles, vec![true; players]);
+            let opponents: Vec<Player> = view.opponents.iter().map(|seat| seat.player).collect();
+            assert_eq!(
+                opponents,
+                Player::PLAYER2.opponents(players).collect::<Vec<_>>()
+            );
+            assert!(view.opponents.iter().all(|seat| seat.cards.is_none()));
+            assert_eq!(table.get_spectator_view(false).players.len(), players);
+        }
+    }
+
+    #[test]
+    fn test_bigger_table_flips_need_every_vote() {
+        let rules = RulesConfig::for_players(Variant::House, 3);
+        let mut table = table_with(rules, 0);
+        table.vote_flip(Player::PLAYER1).unwrap();
+        table.vote_flip(Player::PLAYER3).unwrap();
+        assert!(table.active_piles.0.iter().all(|pile| pile.is_empty()));
+        assert!(table.get_player_view(Player::PLAYER2).opponents[1].flip_requested);
+
+        let events = table.vote_flip(Player::PLAYER2).unwrap();
+        let [_, TableEvent::MiddleFlipped { cards }] = events.as_slice() else {
+            panic!("expected the third vote to flip, got {events:?}");
+        };
+        assert_eq!(cards.len(), 3);
+        assert_eq!(table.active_cards()[2], Some(cards[2]));
+
+        // A pile the table doesn't have can't be played on.
+        assert_eq!(
+            table.place_card(Player::PLAYER1, Side::FOURTH, 0),
+            Err(SE::NoCardToPlace)
+        );
+        while table.player_draw_card(Player::PLAYER1).is_ok() {}
+        assert_eq!(
+            table.place_card(Player::PLAYER1, Side::FOURTH, 0),
+            Err(SE::NoCardToPlaceOn)
+        );
+
+        // The middle runs dry after five flips and is shared out over three piles.
+        for _ in 0..4 {
+            table.flip_middle_cards().unwrap();
+        }
+        let events = table.flip_middle_cards().unwrap();
+        let [TableEvent::MiddleReshuffled { piles }, TableEvent::MiddleFlipped { .. }] =
+            events.as_slice()
+        else {
+            panic!("expected a reshuffle and a flip, got {events:?}");
+        };
+        assert_eq!(piles.len(), 3);
+        assert!(piles.windows(2).all(|pair| pair[0].len() <= pair[1].len()));
+    }
+
+    #[test]
+    fn test_retired_players_are_skipped() {
+        let rules = RulesConfig::for_players(Variant::House, 3);
+        let mut table = table_with(rules, 0);
+        table.vote_flip(Player::PLAYER1).unwrap();
+        table.vote_flip(Player::PLAYER3).unwrap();
+
+        // The flip the others were waiting on happens as soon as PLAYER2 leaves.
+        let events = table.retire(Player::PLAYER2).unwrap();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                TableEvent::PlayerRetired {
+                    player: Player::PLAYER2
+                },
+                TableEvent::MiddleFlipped { .. }
+            ]
+        ));
+        assert_eq!(table.retire(Player::PLAYER2), Ok(Vec::new()));
+        assert_eq!(
+            table.remaining_players().collect::<Vec<_>>(),
+            [Player::PLAYER1, Player::PLAYER3]
+        );
+
+        // Cards left in a retired hand don't keep the table out of a stalemate.
+        table.player_piles = PlayerIndexedPile(vec![Vec::new(); 3]);
+        table.player_hands = PlayerHands(vec![vec![None; 4]; 3]);
+        table.active_piles = SideIndexedPile(vec![vec![Card::new(Rank::Five, Suit::Clubs)]; 3]);
+        table.player_hands[Player::PLAYER2][0] = Some(Card::new(Rank::Six, Suit::Spades));
+        assert!(table.has_legal_play(Player::PLAYER2));
+        assert!(table.is_stalemate());
+    }
+
+    #[test]
+    fn test_bigger_table_games_finish() {
+        for (variant, players) in [(Variant::House, 3), (Variant::Classic, 4)] {
+            for seed in 0..5 {
+                play_out(&mut table_with(
+                    RulesConfig::for_players(variant, players),
+                    seed,
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_team_view() {
+        let mut table = table_with(
+            RulesConfig {
+                teams: true,
+                ..RulesConfig::for_players(Variant::House, 4)
+            },
+            0,
+        );
+        table.player_draw_card(Player::PLAYER3).unwrap();
+
+        let view = table.get_player_view(Player::PLAYER1);
+        let partner = view.partner.unwrap();
+        assert_eq!(partner.player, Player::PLAYER3);
+        assert_eq!(partner.cards.unwrap(), table.player_hands[Player::PLAYER3]);
+        let opponents: Vec<Player> = view.opponents.iter().map(|seat| seat.player).collect();
+        assert_eq!(opponents, [Player::PLAYER2, Player::PLAYER4]);
+        assert!(view.opponents.iter().all(|seat| seat.cards.is_none()));
+        assert_eq!(view.opponent_hand, vec![false; 4]);
+
+        assert_eq!(
+            SpeedTable::from_seed(0)
+                .get_player_view(Player::PLAYER1)
+                .partner,
+            None
+        );
+    }
+
+    #[test]
+    fn test_team_wins_together() {
+        use Rank::*;
+        use Suit::*;
+
+        let mut table = table_with(
+            RulesConfig {
+                teams: true,
+                ..RulesConfig::for_players(Variant::House, 4)
+            },
+            0,
+        );
+        table.player_piles = PlayerIndexedPile(vec![Vec::new(); 4]);
+        table.player_hands = PlayerHands(vec![vec![None; 4]; 4]);
+        table.active_piles = SideIndexedPile(vec![vec![Card::new(Five, Clubs)]; 4]);
+        table.player_hands[Player::PLAYER1][0] = Some(Card::new(Six, Spades));
+        table.player_hands[Player::PLAYER3][2] = Some(Card::new(Four, Hearts));
+        table.player_hands[Player::PLAYER2][0] = Some(Card::new(Jack, Hearts));
+        table.player_hands[Player::PLAYER4][0] = Some(Card::new(Jack, Diamonds));
+
+        // PLAYER1 is out of cards, but their partner isn't yet.
+        let events = table.place_card(Player::PLAYER1, Side::LEFT, 0).unwrap();
+        assert!(!events.contains(&TableEvent::PlayerWon {
+            player: Player::PLAYER1
+        }));
+
+        // Stuck players flip without waiting on the one who has nothing left.
+        table.vote_flip(Player::PLAYER2).unwrap();
+        table.vote_flip(Player::PLAYER3).unwrap();
+        let events = table.vote_flip(Player::PLAYER4).unwrap();
+        assert!(matches!(
+            events.last(),
+            Some(TableEvent::MiddleFlipped { .. })
+        ));
+
+        table.active_piles[Side::RIGHT].push(Card::new(Three, Clubs));
+        let events = table.place_card(Player::PLAYER3, Side::RIGHT, 2).unwrap();
+        assert_eq!(
+            events.last(),
+            Some(&TableEvent::PlayerWon {
+                player: Player::PLAYER3
+            })
+        );
+    }
+
+    /// Every card on the table, wherever it is.
+    fn table_cards(table: &SpeedTable) -> Vec<Card> {
+        let hands = table.player_hands.0.iter().flatten().flatten();
+        let piles = [
+            &table.player_piles.0,
+            &table.middle_piles.0,
+            &table.active_piles.0,
+        ]
+        .into_iter()
+        .flatten()
+        .flatten();
+        hands.chain(piles).copied().collect()
+    }
+
+    #[test]
+    fn test_jokers_survive_reshuffles() {
+        let mut table = table_with(RulesConfig::default().with_jokers(), 0);
+        assert_eq!(table.player_piles[Player::PLAYER1].len(), 20);
+        let cards = table_cards(&table);
+        assert_eq!(cards.len(), 54);
+        assert!(Card::JOKERS.iter().all(|joker| cards.contains(joker)));
+
+        for _ in 0..8 {
+            table.flip_middle_cards().unwrap();
+        }
+        assert_eq!(table.active_piles[Side::LEFT].len(), 1);
+        let cards = table_cards(&table);
+        assert_eq!(cards.len(), 54);
+        assert!(Card::JOKERS.iter().all(|joker| cards.contains(joker)));
+
+        assert!(!table_cards(&SpeedTable::from_seed(0))
+            .iter()
+            .any(Card::is_joker));
+    }
+
+    #[test]
+    fn test_joker_placement_and_win() {
+        use Rank::*;
+        use Suit::*;
+
+        let [red, black] = Card::JOKERS;
+        let mut table = table_with(RulesConfig::default().with_jokers(), 0);
+        table.player_piles = PlayerIndexedPile(vec![Vec::new(); 2]);
+        table.active_piles = SideIndexedPile(vec![
+            vec![Card::new(Seven, Clubs)],
+            vec![Card::new(Ace, Hearts)],
+        ]);
+        table.player_hands = PlayerHands(vec![
+            vec![Some(red), Some(black), None, None],
+            vec![Some(Card::new(King, Hearts)), None, None, None],
+        ]);
+
+        // Anything but the jokers is played by rank as usual.
+        let legal = table.legal_actions(Player::PLAYER2);
+        assert_eq!(legal.placements, [(0, Side::RIGHT)]);
+        assert_eq!(
+            table.legal_actions(Player::PLAYER1).placements,
+            [
+                (0, Side::LEFT),
+                (0, Side::RIGHT),
+                (1, Side::LEFT),
+                (1, Side::RIGHT)
+            ]
+        );
+
+        // A joker goes on a Seven, and a King goes on the joker.
+        let events = table.place_card(Player::PLAYER1, Side::LEFT, 0).unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(table.place_card(Player::PLAYER2, Side::LEFT, 0).is_ok());
+        assert_eq!(
+            table.active_piles[Side::LEFT].last(),
+            Some(&Card::new(King, Hearts))
+        );
+
+        // Going out on a joker wins like any other last card.
+        let events = table.place_card(Player::PLAYER1, Side::LEFT, 1).unwrap();
+        assert_eq!(
+            events.last(),
+            Some(&TableEvent::PlayerWon {
+                player: Player::PLAYER1
+            })
+        );
+    }
+
+    #[test]
+    fn test_seeded_reshuffle_is_reproducible() {
+        let mut first = SpeedTable::from_seed(42);
+        let mut second = SpeedTable::from_seed(first.seed());
+
+        // Enough flips to go through the reshuffle twice.
+        for _ in 0..20 {
+            let _ = first.flip_middle_cards();
+            let _ = second.flip_middle_cards();
+        }
+
+        assert_eq!(
+            first.middle_piles[Side::LEFT],
+            second.middle_piles[Side::LEFT]
+        );
+        assert_eq!(
+            first.middle_piles[Side::RIGHT],
+            second.middle_piles[Side::RIGHT]
+        );
+        assert_eq!(
+            first.get_player_view(Player::PLAYER1),
+            second.get_player_view(Player::PLAYER1)
+        );
+        assert_ne!(
+            SpeedTable::from_seed(43).player_piles[Player::PLAYER1],
+            first.player_piles[Player::PLAYER1]
+        );
+    }
 }
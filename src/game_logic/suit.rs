@@ -6,9 +6,13 @@ pub enum Suit {
     Spades,
     Clubs,
     Hearts,
+    /// The colours of the two jokers, which have no suit of their own.
+    Red,
+    Black,
 }
 
 impl Suit {
+    /// The four suits of the 52-card deck.
     pub fn iter() -> impl Iterator<Item = Suit> {
         [Suit::Diamonds, Suit::Spades, Suit::Clubs, Suit::Hearts]
             .iter()
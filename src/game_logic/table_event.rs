@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use super::{card::Card, player::Player, side::Side};
+
+/// Something that changed on the table, returned in order by each `SpeedTable`
+/// mutation. Applying a game's events one after another from the deal rebuilds
+/// the table exactly.
+///
+/// Pending flip votes are dropped whenever a card is placed or the middle cards
+/// flip; that is implied by `CardPlaced` and `MiddleFlipped` rather than being
+/// an event of its own.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum TableEvent {
+    /// `card` came off the top of `player`'s pile into their hand at `hand_index`.
+    CardDrawn {
+        player: Player,
+        hand_index: usize,
+        card: Card,
+    },
+    /// `card` left `player`'s hand at `hand_index` for the active pile on `side`.
+    CardPlaced {
+        player: Player,
+        hand_index: usize,
+        side: Side,
+        card: Card,
+    },
+    /// `player` asked for the middle cards to be flipped.
+    FlipVoted { player: Player },
+    /// A middle pile ran out and the middle was rebuilt into these piles,
+    /// bottom card first and indexed by `Side as usize`. Every active pile is
+    /// empty afterwards except when `reshuffle_active_piles` is off and the
+    /// leftover middle cards were enough to share out on their own; then the
+    /// active piles are untouched. In the classic variant each active pile was
+    /// shuffled under what remained of its own side stack instead, and every
+    /// active pile is empty afterwards.
+    MiddleReshuffled { piles: Vec<Vec<Card>> },
+    /// The top middle cards were turned onto the active piles, indexed by
+    /// `Side as usize`.
+    MiddleFlipped { cards: Vec<Card> },
+    /// `player` gave up their seat. Their cards stay put, and they no longer
+    /// count for flip votes or stalemates.
+    PlayerRetired { player: Player },
+    /// `player` has no cards left in hand or pile, and in a team game neither
+    /// has their partner.
+    PlayerWon { player: Player },
+}
@@ -0,0 +1,81 @@
+use rand::{rngs::StdRng, SeedableRng};
+use tokio::{
+    sync::mpsc::{self, UnboundedSender},
+    time::Instant,
+};
+
+use super::{
+    player_link::{LinkEvent, LinkEventKind},
+    wait_until,
+};
+use crate::{
+    bot::{Decision, Strategy, StrategyContext},
+    game_logic::Player,
+    RejectReason, ServerAction, ServerMessage,
+};
+
+/// Seat a server-side bot. It only sees the messages its player would be sent,
+/// and asks `strategy` what to do whenever one arrives or a wait it asked for
+/// runs out.
+pub fn spawn_bot_link(
+    mut strategy: Box<dyn Strategy>,
+    player: Player,
+    generation: u32,
+    events: UnboundedSender<LinkEvent>,
+) -> UnboundedSender<ServerMessage> {
+    let (messages, mut incoming) = mpsc::unbounded_channel::<ServerMessage>();
+
+    tokio::spawn(async move {
+        let mut rng = StdRng::from_entropy();
+        let started = Instant::now();
+        let mut last_change = started;
+        let mut view = None;
+        let mut can_vote_flip = true;
+        let mut last_rejection = None;
+        let mut next_decision_at: Option<Instant> = None;
+
+        loop {
+            tokio::select! {
+                message = incoming.recv() => {
+                    // The session has finished with this seat.
+                    let Some(message) = message else { return };
+                    match message.action {
+                        ServerAction::MoveRejected(reason) => {
+                            can_vote_flip &= reason != RejectReason::ManualFlipDisabled;
+                            last_rejection = Some(reason);
+                        }
+                        _ => {
+                            last_change = Instant::now();
+                            last_rejection = None;
+                        }
+                    }
+                    view = Some(message.player_view);
+                    next_decision_at = Some(Instant::now());
+                }
+                _ = wait_until(next_decision_at) => {
+                    next_decision_at = None;
+                    let Some(view) = &view else { continue };
+                    let now = Instant::now();
+                    let context = StrategyContext {
+                        elapsed: now - started,
+                        since_last_change: now - last_change,
+                        can_vote_flip,
+                        last_rejection,
+                    };
+                    match strategy.decide(view, &context, &mut rng) {
+                        Decision::Act(action) => {
+                            let kind = LinkEventKind::Action(action);
+                            if events.send(LinkEvent { player, generation, kind }).is_err() {
+                                return;
+                            }
+                        }
+                        Decision::Wait(delay) => next_decision_at = now.checked_add(delay),
+                        Decision::WaitForChange => {}
+                    }
+                }
+            }
+        }
+    });
+
+    messages
+}
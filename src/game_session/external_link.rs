@@ -0,0 +1,102 @@
+use std::{collections::VecDeque, process::Stdio};
+
+use anyhow::{bail, Context, Result};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::Command,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{timeout, Instant},
+};
+
+use super::{
+    player_link::{LinkEvent, LinkEventKind},
+    wait_until,
+};
+use crate::{bot::ExternalBot, game_logic::Player, PlayerAction, ServerMessage};
+
+/// Seat an external bot process. Messages pushed into the returned sender are
+/// written to its stdin, its replies come back through `events`, and anything
+/// that goes wrong with the process is reported as a forfeit.
+pub fn spawn_external_link(
+    bot: ExternalBot,
+    player: Player,
+    generation: u32,
+    events: UnboundedSender<LinkEvent>,
+) -> UnboundedSender<ServerMessage> {
+    let (messages, outgoing) = mpsc::unbounded_channel::<ServerMessage>();
+
+    tokio::spawn(async move {
+        if let Err(e) = run_process(&bot, outgoing, player, generation, &events).await {
+            println!("External bot {player:?} ({}) forfeits: {e:#}", bot.program);
+            let kind = LinkEventKind::Forfeit;
+            let _ = events.send(LinkEvent {
+                player,
+                generation,
+                kind,
+            });
+        }
+    });
+
+    messages
+}
+
+async fn run_process(
+    bot: &ExternalBot,
+    mut outgoing: UnboundedReceiver<ServerMessage>,
+    player: Player,
+    generation: u32,
+    events: &UnboundedSender<LinkEvent>,
+) -> Result<()> {
+    let mut child = Command::new(&bot.program)
+        .args(&bot.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("could not start {}", bot.program))?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut replies = BufReader::new(child.stdout.take().unwrap()).lines();
+    // When each message the bot hasn't answered yet has to be answered by.
+    let mut deadlines = VecDeque::new();
+
+    loop {
+        tokio::select! {
+            message = outgoing.recv() => {
+                // The session has finished with this seat.
+                let Some(message) = message else { break };
+                let mut line = serde_json::to_string(&message).unwrap();
+                line.push('\n');
+                stdin
+                    .write_all(line.as_bytes())
+                    .await
+                    .context("stopped reading its input")?;
+                deadlines.push_back(Instant::now() + bot.move_limit);
+            }
+            reply = replies.next_line() => {
+                let line = reply
+                    .context("could not read its output")?
+                    .context("exited during the game")?;
+                let action: Option<PlayerAction> = serde_json::from_str(&line)
+                    .with_context(|| format!("sent a malformed reply {line:?}"))?;
+                deadlines.pop_front();
+                if let Some(action) = action {
+                    let kind = LinkEventKind::Action(action);
+                    if events.send(LinkEvent { player, generation, kind }).is_err() {
+                        break;
+                    }
+                }
+            }
+            _ = wait_until(deadlines.front().copied()) => {
+                bail!("took longer than {:?} to reply", bot.move_limit);
+            }
+        }
+    }
+
+    // Closing stdin lets the bot exit by itself, but it doesn't get to linger.
+    drop(stdin);
+    if timeout(bot.move_limit, child.wait()).await.is_err() {
+        let _ = child.kill().await;
+    }
+    Ok(())
+}
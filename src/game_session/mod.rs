@@ -0,0 +1,677 @@
+use std::ops::{Index, IndexMut};
+
+use anyhow::{ensure, Result};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bot::{ExternalBot, Strategy},
+    game_logic::{Player, SpeedTable, TableEvent},
+    history::{GameHistory, GameRecorder},
+    PlayerAction, PlayerConnection, RejectReason, RoomSettings, ServerAction, ServerMessage,
+    SpectatorAction, SpectatorMessage,
+};
+
+mod player_link;
+use player_link::{spawn_socket_link, LinkEvent, LinkEventKind};
+
+mod bot_link;
+use bot_link::spawn_bot_link;
+
+mod external_link;
+use external_link::spawn_external_link;
+
+mod spectator_link;
+use spectator_link::spawn_spectator_link;
+
+mod registry;
+pub use registry::SessionRegistry;
+
+mod outcome;
+use outcome::GameStats;
+pub use outcome::{GameEnd, GameOutcome};
+
+/// How long a disconnected player has to come back with their resume token before
+/// they forfeit the game.
+const RESUME_GRACE: Duration = Duration::from_secs(30);
+
+/// How long players get to notice a stalemate before the server flips for them.
+const STALEMATE_COUNTDOWN: Duration = Duration::from_secs(3);
+
+/// Whoever fills a seat at the table.
+// Only a few are ever made per game, so the size difference doesn't matter.
+#[allow(clippy::large_enum_variant)]
+pub enum Participant {
+    /// A client connected over WebSocket.
+    Remote(PlayerConnection),
+    /// A bot played by the server itself.
+    Bot(Box<dyn Strategy>),
+    /// A bot process the server runs and talks to over stdio.
+    External(ExternalBot),
+}
+
+impl Participant {
+    /// How the player shows up in the game history.
+    fn name(&self) -> String {
+        match self {
+            Participant::Remote(connection) => connection
+                .get_ref()
+                .peer_addr()
+                .map_or_else(|_| "unknown".to_string(), |address| address.to_string()),
+            Participant::Bot(_) => "bot".to_string(),
+            Participant::External(bot) => format!("external bot {}", bot.program),
+        }
+    }
+}
+
+struct Seat {
+    link: Option<UnboundedSender<ServerMessage>>,
+    generation: u32,
+    resume_token: String,
+    disconnected_at: Option<Instant>,
+}
+
+impl Seat {
+    fn connect(
+        &mut self,
+        participant: Participant,
+        player: Player,
+        events: &UnboundedSender<LinkEvent>,
+    ) {
+        self.generation += 1;
+        let events = events.clone();
+        self.link = Some(match participant {
+            Participant::Remote(connection) => {
+                spawn_socket_link(connection, player, self.generation, events)
+            }
+            Participant::Bot(strategy) => spawn_bot_link(strategy, player, self.generation, events),
+            Participant::External(bot) => spawn_external_link(bot, player, self.generation, events),
+        });
+        self.disconnected_at = None;
+    }
+
+    fn send(&self, message: ServerMessage) {
+        // A failed send means the link is shutting down and will report it shortly.
+        if let Some(link) = &self.link {
+            let _ = link.send(message);
+        }
+    }
+}
+
+/// One seat per player, indexed by `Player as usize`.
+struct Seats(Vec<Seat>);
+
+impl Seats {
+    fn next_grace_expiry(&self) -> Option<Instant> {
+        self.0
+            .iter()
+            .filter_map(|seat| seat.disconnected_at)
+            .min()
+            .map(|disconnected_at| disconnected_at + RESUME_GRACE)
+    }
+
+    /// The player who has been gone past the grace period, if any. When several
+    /// have, the one who left first forfeits.
+    fn forfeiting_player(&self, now: Instant) -> Option<Player> {
+        Player::seated(self.0.len())
+            .filter(|player| {
+                self[*player]
+                    .disconnected_at
+                    .is_some_and(|disconnected_at| disconnected_at + RESUME_GRACE <= now)
+            })
+            .min_by_key(|player| self[*player].disconnected_at)
+    }
+}
+
+impl Index<Player> for Seats {
+    type Output = Seat;
+
+    fn index(&self, player: Player) -> &Self::Output {
+        &self.0[player as usize]
+    }
+}
+
+impl IndexMut<Player> for Seats {
+    fn index_mut(&mut self, player: Player) -> &mut Self::Output {
+        &mut self.0[player as usize]
+    }
+}
+
+/// Everything that can reach a running session from the outside.
+struct SessionInbox {
+    events: UnboundedReceiver<LinkEvent>,
+    resumes: UnboundedReceiver<registry::Resume>,
+    spectators: UnboundedReceiver<PlayerConnection>,
+}
+
+/// Play out a game between `participants`, seated in order and as many as the
+/// rules ask for, recording it in `history`.
+/// Games started from a private room pass its code so that spectators can find
+/// the game while it runs.
+pub async fn start_game(
+    participants: Vec<Participant>,
+    room_code: Option<String>,
+    settings: RoomSettings,
+    registry: SessionRegistry,
+    history: GameHistory,
+) -> Result<GameOutcome> {
+    let (events_sender, events) = mpsc::unbounded_channel();
+    let (resume_sender, resumes) = mpsc::unbounded_channel();
+    let (spectator_sender, spectators) = mpsc::unbounded_channel();
+    let mut inbox = SessionInbox {
+        events,
+        resumes,
+        spectators,
+    };
+
+    let players = settings.rules.players;
+    ensure!(
+        participants.len() == players,
+        "the rules seat {players} players but {} were given",
+        participants.len()
+    );
+    let table = SpeedTable::with_rules(settings.rules, rand::random())?;
+    println!("Dealing table with seed {}", table.seed());
+    let names = participants.iter().map(Participant::name).collect();
+    let recorder = history.record_game(table.seed(), names, &settings).await?;
+
+    let seats = table
+        .players()
+        .map(|player| Seat {
+            link: None,
+            generation: 0,
+            resume_token: registry.issue_resume_token(player, resume_sender.clone()),
+            disconnected_at: None,
+        })
+        .collect();
+    let mut session = GameSession {
+        stats: GameStats::new(table.seed(), players),
+        table,
+        seats: Seats(seats),
+        spectators: Vec::new(),
+        settings,
+        flip_countdown: None,
+        recorder,
+        events_sender,
+    };
+    for (player, participant) in Player::ALL.into_iter().zip(participants) {
+        session.connect(player, participant);
+    }
+    if let Some(code) = &room_code {
+        registry.open_to_spectators(code, spectator_sender);
+    }
+
+    let outcome = session.run(&mut inbox).await;
+
+    for seat in &session.seats.0 {
+        registry.revoke_resume_token(&seat.resume_token);
+    }
+    if let Some(code) = &room_code {
+        registry.close_to_spectators(code);
+    }
+    // Dropping the session closes every socket once the final messages are out.
+    Ok(outcome)
+}
+
+struct GameSession {
+    table: SpeedTable,
+    seats: Seats,
+    spectators: Vec<UnboundedSender<SpectatorMessage>>,
+    settings: RoomSettings,
+    /// When the automatic flip for the current stalemate is due.
+    flip_countdown: Option<Instant>,
+    stats: GameStats,
+    recorder: GameRecorder,
+    events_sender: UnboundedSender<LinkEvent>,
+}
+
+impl GameSession {
+    fn connect(&mut self, player: Player, participant: Participant) {
+        self.seats[player].connect(participant, player, &self.events_sender);
+    }
+
+    async fn run(&mut self, inbox: &mut SessionInbox) -> GameOutcome {
+        for player in self.table.players() {
+            self.seats[player].send(ServerMessage {
+                action: ServerAction::SetBoard,
+                player_view: self.table.get_player_view(player),
+                resume_token: Some(self.seats[player].resume_token.clone()),
+            });
+        }
+        self.check_for_stalemate();
+
+        loop {
+            let grace_expiry = self.seats.next_grace_expiry();
+
+            tokio::select! {
+                Some(event) = inbox.events.recv() => {
+                    let player = event.player;
+                    if event.generation != self.seats[player].generation
+                        || self.table.has_retired(player)
+                    {
+                        // Left over from a connection that has since been replaced,
+                        // or from a player who is already out of the game.
+                        continue;
+                    }
+
+                    match event.kind {
+                        LinkEventKind::Action(PlayerAction::Forfeit) | LinkEventKind::Forfeit => {
+                            println!("{player:?} forfeited");
+                            if let Some(outcome) = self.forfeit(player) {
+                                return outcome;
+                            }
+                        }
+                        LinkEventKind::Action(player_move) => {
+                            if self.apply_player_move(player, player_move) {
+                                return self.finish(player, GameEnd::Won);
+                            }
+                        }
+                        LinkEventKind::Left => {
+                            println!("{player:?} disconnected");
+                            self.seats[player].link = None;
+                            self.seats[player].disconnected_at = Some(Instant::now());
+                            for opponent in self.opponents(player) {
+                                self.send_single_message(
+                                    opponent,
+                                    ServerAction::OpponentDisconnected,
+                                );
+                            }
+                        }
+                    }
+                }
+                Some(connection) = inbox.spectators.recv() => {
+                    let first_message = self.spectator_message(SpectatorAction::SetBoard);
+                    self.spectators
+                        .push(spawn_spectator_link(connection, first_message));
+                }
+                Some((player, connection)) = inbox.resumes.recv() => {
+                    if self.table.has_retired(player) {
+                        // Their seat is gone; dropping the connection closes it.
+                        continue;
+                    }
+                    println!("{player:?} reconnected");
+                    self.connect(player, Participant::Remote(connection));
+                    self.send_player_message(
+                        player,
+                        ServerAction::Resumed,
+                        ServerAction::OpponentReconnected,
+                    );
+                }
+                _ = wait_until(self.flip_countdown) => self.flip_after_countdown(),
+                _ = wait_until(grace_expiry) => {
+                    let Some(forfeited) = self.seats.forfeiting_player(Instant::now()) else {
+                        continue;
+                    };
+                    println!("{forfeited:?} forfeited by not reconnecting in time");
+                    if let Some(outcome) = self.forfeit(forfeited) {
+                        return outcome;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply a move and tell both players about it, or only the mover if it was
+    /// rejected. Returns whether the move won the game.
+    fn apply_player_move(&mut self, player: Player, player_move: PlayerAction) -> bool {
+        if matches!(player_move, PlayerAction::Flip)
+            && !self.settings.flip_mode.allows_manual_flip()
+        {
+            let reason = RejectReason::ManualFlipDisabled;
+            self.send_single_message(player, ServerAction::MoveRejected(reason));
+            return false;
+        }
+
+        let events = match player_move.apply(&mut self.table, player) {
+            Ok(events) => events,
+            Err(error) => {
+                // Nothing changed on the table, so the opponent doesn't hear about it.
+                let reason = RejectReason::for_error(&error);
+                self.send_single_message(player, ServerAction::MoveRejected(reason));
+                return false;
+            }
+        };
+        self.stats.record(&events);
+        self.record_action(Some(player), player_move);
+
+        if events.contains(&TableEvent::PlayerWon { player }) {
+            return true;
+        }
+
+        // A vote that didn't flip anything yet is everyone else's cue to flip too.
+        let flipped = events
+            .iter()
+            .any(|event| matches!(event, TableEvent::MiddleFlipped { .. }));
+        let other_player_action = if matches!(player_move, PlayerAction::Flip) && !flipped {
+            ServerAction::FlipRequested
+        } else {
+            ServerAction::NormalMove
+        };
+        self.send_player_message(player, ServerAction::NormalMove, other_player_action);
+        self.broadcast_to_spectators(SpectatorAction::NormalMove);
+        self.check_for_stalemate();
+        false
+    }
+
+    /// Take `player` out of the game. With three or four players and no teams
+    /// the others play on until only one is left, who wins; otherwise the
+    /// forfeit ends the game straight away. Returns the outcome if it ended.
+    fn forfeit(&mut self, player: Player) -> Option<GameOutcome> {
+        if let Ok(events) = self.table.retire(player) {
+            self.stats.record(&events);
+        }
+        self.record_action(Some(player), PlayerAction::Forfeit);
+        if self.table.rules().teams || self.table.remaining_players().count() == 1 {
+            let winner = self.leader_without(player);
+            return Some(self.finish(winner, GameEnd::Forfeit));
+        }
+
+        self.send_single_message(player, ServerAction::GameLost);
+        self.seats[player].link = None;
+        self.seats[player].disconnected_at = None;
+        self.send_to_everyone(ServerAction::NormalMove);
+        self.broadcast_to_spectators(SpectatorAction::NormalMove);
+        self.check_for_stalemate();
+        None
+    }
+
+    /// Start the automatic flip countdown when the table has just got stuck, or
+    /// call it off if the players flipped their way out of it first.
+    fn check_for_stalemate(&mut self) {
+        if !self.settings.flip_mode.allows_automatic_flip() {
+            return;
+        }
+        if !self.table.is_stalemate() {
+            self.flip_countdown = None;
+            return;
+        }
+        if self.flip_countdown.is_some() {
+            return;
+        }
+
+        self.flip_countdown = Some(Instant::now() + STALEMATE_COUNTDOWN);
+        let seconds = STALEMATE_COUNTDOWN.as_secs() as u32;
+        self.send_to_everyone(ServerAction::StalemateCountdown(seconds));
+        self.broadcast_to_spectators(SpectatorAction::StalemateCountdown(seconds));
+    }
+
+    fn flip_after_countdown(&mut self) {
+        self.flip_countdown = None;
+        if let Ok(events) = self.table.flip_middle_cards() {
+            self.stats.record(&events);
+            self.record_action(None, PlayerAction::Flip);
+        }
+
+        self.send_to_everyone(ServerAction::NormalMove);
+        self.broadcast_to_spectators(SpectatorAction::NormalMove);
+        // The new active cards may leave everyone stuck all over again.
+        self.check_for_stalemate();
+    }
+
+    /// Announce the winner and send every player the final summary. No further
+    /// moves are read once this has been called.
+    fn finish(&mut self, winner: Player, end: GameEnd) -> GameOutcome {
+        let outcome = self
+            .stats
+            .finish(winner, self.table.rules().partner(winner), end);
+        if let Err(e) = self.recorder.record_outcome(&outcome) {
+            println!(
+                "Could not record the end of game {}: {e}",
+                self.recorder.game_id()
+            );
+        }
+        for player in self.table.players() {
+            let result = if outcome.is_winner(player) {
+                ServerAction::GameWon
+            } else {
+                ServerAction::GameLost
+            };
+            self.send_single_message(player, result);
+        }
+        for player in self.table.players() {
+            self.send_single_message(player, ServerAction::GameOver(outcome.summary_for(player)));
+        }
+        self.broadcast_to_spectators(SpectatorAction::PlayerWon(winner));
+        outcome
+    }
+
+    /// A game is worth finishing even if the history can't be written to.
+    fn record_action(&mut self, player: Option<Player>, action: PlayerAction) {
+        if let Err(e) = self.recorder.record_action(player, action) {
+            println!(
+                "Could not record a move in game {}: {e}",
+                self.recorder.game_id()
+            );
+        }
+    }
+
+    fn send_player_message(
+        &self,
+        moved_player: Player,
+        player_action: ServerAction,
+        other_player_action: ServerAction,
+    ) {
+        self.send_single_message(moved_player, player_action);
+        for opponent in self.opponents(moved_player) {
+            self.send_single_message(opponent, other_player_action);
+        }
+    }
+
+    fn send_to_everyone(&self, action: ServerAction) {
+        for player in self.table.players() {
+            self.send_single_message(player, action);
+        }
+    }
+
+    fn opponents(&self, player: Player) -> impl Iterator<Item = Player> {
+        player.opponents(self.table.rules().players)
+    }
+
+    /// Who wins when `forfeited` drops out: whoever else is still in and has
+    /// the fewest cards left, the earlier seat on a tie. With only one player
+    /// left that's them, and in a team game the other team wins.
+    fn leader_without(&self, forfeited: Player) -> Player {
+        let partner = self.table.rules().partner(forfeited);
+        self.table
+            .remaining_players()
+            .filter(|&player| player != forfeited && Some(player) != partner)
+            .min_by_key(|&player| self.table.cards_left(player))
+            .expect("every table seats at least two players")
+    }
+
+    fn send_single_message(&self, player: Player, action: ServerAction) {
+        self.seats[player].send(ServerMessage {
+            action,
+            player_view: self.table.get_player_view(player),
+            resume_token: None,
+        });
+    }
+
+    fn spectator_message(&self, action: SpectatorAction) -> SpectatorMessage {
+        SpectatorMessage {
+            action,
+            spectator_view: self
+                .table
+                .get_spectator_view(self.settings.reveal_hands_to_spectators),
+        }
+    }
+
+    fn broadcast_to_spectators(&mut self, action: SpectatorAction) {
+        let message = self.spectator_message(action);
+        // Spectators that went away are dropped the first time a send to them fails.
+        self.spectators
+            .retain(|spectator| spectator.send(message.clone()).is_ok());
+    }
+}
+
+pub(crate) async fn wait_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use super::*;
+    use crate::{
+        bot::{Decision, StrategyContext},
+        game_logic::{PlayerView, RulesConfig, Variant},
+    };
+
+    fn seat(disconnected_at: Option<Instant>) -> Seat {
+        Seat {
+            link: None,
+            generation: 1,
+            resume_token: String::new(),
+            disconnected_at,
+        }
+    }
+
+    #[test]
+    fn test_forfeiting_player() {
+        let now = Instant::now();
+        let expired = now - RESUME_GRACE - Duration::from_secs(1);
+        let long_expired = expired - Duration::from_secs(5);
+
+        let seats = Seats(vec![seat(None), seat(Some(now))]);
+        assert_eq!(seats.forfeiting_player(now), None);
+        assert_eq!(seats.next_grace_expiry(), Some(now + RESUME_GRACE));
+
+        let seats = Seats(vec![seat(None), seat(Some(expired))]);
+        assert_eq!(seats.forfeiting_player(now), Some(Player::PLAYER2));
+
+        let seats = Seats(vec![seat(Some(expired)), seat(Some(long_expired))]);
+        assert_eq!(seats.forfeiting_player(now), Some(Player::PLAYER2));
+
+        let seats = Seats(vec![seat(None), seat(Some(now)), seat(Some(expired))]);
+        assert_eq!(seats.forfeiting_player(now), Some(Player::PLAYER3));
+    }
+
+    /// Sits at the table without ever moving.
+    struct Idle;
+
+    impl Strategy for Idle {
+        fn decide(&mut self, _: &PlayerView, _: &StrategyContext, _: &mut dyn RngCore) -> Decision {
+            Decision::WaitForChange
+        }
+    }
+
+    fn idle() -> Participant {
+        Participant::Bot(Box::new(Idle))
+    }
+
+    /// An external bot running `script` under `sh`, with a short move limit.
+    fn script_bot(script: &str) -> Participant {
+        Participant::External(ExternalBot {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            move_limit: Duration::from_millis(200),
+        })
+    }
+
+    /// An external bot that exits straight away, forfeiting its seat.
+    fn quitting_bot() -> Participant {
+        script_bot("exit 0")
+    }
+
+    /// Start a game with a fresh registry and history and wait for it to end.
+    async fn run_to_outcome(
+        participants: Vec<Participant>,
+        settings: RoomSettings,
+    ) -> Result<GameOutcome> {
+        let game = start_game(
+            participants,
+            None,
+            settings,
+            SessionRegistry::default(),
+            GameHistory::open_in_memory().unwrap(),
+        );
+        tokio::time::timeout(Duration::from_secs(5), game)
+            .await
+            .expect("the game should have ended")
+    }
+
+    async fn play_against(script: &str) -> GameOutcome {
+        run_to_outcome(vec![idle(), script_bot(script)], RoomSettings::default())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_external_bot_forfeits() {
+        let scripts = [
+            "exit 0",
+            "while read line; do :; done",
+            "while read line; do echo oops; done",
+        ];
+        for script in scripts {
+            let outcome = play_against(script).await;
+            assert_eq!(outcome.winner, Player::PLAYER1, "{script}");
+            assert_eq!(outcome.end, GameEnd::Forfeit, "{script}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bigger_table_forfeit() {
+        let settings = RoomSettings {
+            rules: RulesConfig::for_players(Variant::House, 3),
+            ..RoomSettings::default()
+        };
+        // The other two play on after one of them leaves.
+        let one_left = start_game(
+            vec![quitting_bot(), idle(), idle()],
+            None,
+            settings.clone(),
+            SessionRegistry::default(),
+            GameHistory::open_in_memory().unwrap(),
+        );
+        assert!(tokio::time::timeout(Duration::from_secs(1), one_left)
+            .await
+            .is_err());
+
+        let participants = vec![quitting_bot(), idle(), quitting_bot()];
+        let outcome = run_to_outcome(participants, settings.clone())
+            .await
+            .unwrap();
+        // The last one sitting wins.
+        assert_eq!(outcome.winner, Player::PLAYER2);
+        assert_eq!(outcome.end, GameEnd::Forfeit);
+        assert_eq!(outcome.cards_played, [0, 0, 0]);
+
+        let too_many = vec![quitting_bot(), idle(), idle(), idle()];
+        assert!(run_to_outcome(too_many, settings).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_team_forfeit() {
+        let settings = RoomSettings {
+            rules: RulesConfig {
+                teams: true,
+                ..RulesConfig::for_players(Variant::House, 4)
+            },
+            ..RoomSettings::default()
+        };
+        let participants = vec![idle(), quitting_bot(), idle(), idle()];
+        let outcome = run_to_outcome(participants, settings).await.unwrap();
+        // The forfeit loses the game for PLAYER2's partner too.
+        assert_eq!(outcome.winner, Player::PLAYER1);
+        assert_eq!(outcome.partner, Some(Player::PLAYER3));
+        assert!(!outcome.is_winner(Player::PLAYER4));
+    }
+
+    #[tokio::test]
+    async fn test_external_bot_plays_on() {
+        // A bot that answers every message in time keeps its seat.
+        let bot = "while read line; do echo null; done";
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), play_against(bot))
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use tokio::time::{Duration, Instant};
+
+use crate::{
+    game_logic::{Player, TableEvent},
+    GameSummary,
+};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum GameEnd {
+    /// The winner played their last card.
+    Won,
+    /// The loser gave up, disconnected and didn't come back within the grace
+    /// period, or was an external bot that crashed, broke the protocol or ran
+    /// out of time. In a free-for-all of three or four, every player but the
+    /// winner left that way.
+    Forfeit,
+}
+
+/// What a finished session reports back to whoever started it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameOutcome {
+    /// Seed the table was dealt from, enough to reproduce the game from its moves.
+    pub seed: u64,
+    pub winner: Player,
+    /// The winner's partner in a team game, who wins with them.
+    pub partner: Option<Player>,
+    pub end: GameEnd,
+    pub duration: Duration,
+    /// Cards placed on the active piles, indexed by `Player as usize`.
+    pub cards_played: Vec<u32>,
+    pub middle_flips: u32,
+}
+
+impl GameOutcome {
+    pub fn is_winner(&self, player: Player) -> bool {
+        self.winner == player || self.partner == Some(player)
+    }
+
+    pub fn summary_for(&self, player: Player) -> GameSummary {
+        GameSummary {
+            won: self.is_winner(player),
+            duration_ms: self.duration.as_millis() as u64,
+            cards_played: self.cards_played[player as usize],
+            opponent_cards_played: self.cards_played.iter().sum::<u32>()
+                - self.cards_played[player as usize],
+            middle_flips: self.middle_flips,
+        }
+    }
+}
+
+/// Running totals for a game that is still being played.
+pub struct GameStats {
+    seed: u64,
+    started: Instant,
+    cards_played: Vec<u32>,
+    middle_flips: u32,
+}
+
+impl GameStats {
+    pub fn new(seed: u64, players: usize) -> GameStats {
+        GameStats {
+            seed,
+            started: Instant::now(),
+            cards_played: vec![0; players],
+            middle_flips: 0,
+        }
+    }
+
+    pub fn record(&mut self, events: &[TableEvent]) {
+        for event in events {
+            match event {
+                TableEvent::CardPlaced { player, .. } => self.cards_played[*player as usize] += 1,
+                TableEvent::MiddleFlipped { .. } => self.middle_flips += 1,
+                _ => {}
+            }
+        }
+    }
+
+    pub fn finish(&self, winner: Player, partner: Option<Player>, end: GameEnd) -> GameOutcome {
+        GameOutcome {
+            seed: self.seed,
+            winner,
+            partner,
+            end,
+            duration: self.started.elapsed(),
+            cards_played: self.cards_played.clone(),
+            middle_flips: self.middle_flips,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_for() {
+        let outcome = GameOutcome {
+            seed: 7,
+            winner: Player::PLAYER2,
+            partner: None,
+            end: GameEnd::Won,
+            duration: Duration::from_millis(61_500),
+            cards_played: vec![12, 19],
+            middle_flips: 4,
+        };
+
+        assert_eq!(
+            outcome.summary_for(Player::PLAYER1),
+            GameSummary {
+                won: false,
+                duration_ms: 61_500,
+                cards_played: 12,
+                opponent_cards_played: 19,
+                middle_flips: 4,
+            }
+        );
+        assert!(outcome.summary_for(Player::PLAYER2).won);
+        assert_eq!(outcome.summary_for(Player::PLAYER2).cards_played, 19);
+
+        let three_players = GameOutcome {
+            cards_played: vec![12, 19, 7],
+            ..outcome
+        };
+        let summary = three_players.summary_for(Player::PLAYER3);
+        assert_eq!(summary.cards_played, 7);
+        assert_eq!(summary.opponent_cards_played, 31);
+
+        let teams = GameOutcome {
+            cards_played: vec![12, 19, 7, 3],
+            partner: Some(Player::PLAYER4),
+            ..outcome
+        };
+        assert!(teams.summary_for(Player::PLAYER4).won);
+        assert!(!teams.summary_for(Player::PLAYER3).won);
+    }
+}
@@ -0,0 +1,115 @@
+use futures_util::{SinkExt, StreamExt};
+use tokio::{
+    sync::mpsc::{self, UnboundedSender},
+    time::{timeout, Duration, Instant},
+};
+use tokio_tungstenite::tungstenite::{
+    protocol::{frame::coding::CloseCode, CloseFrame},
+    Message,
+};
+
+use crate::{game_logic::Player, PlayerAction, PlayerConnection, ServerMessage};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// A client that sends nothing at all, not even a pong, for this long is treated
+/// as gone. This is what catches half-open connections.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Something that happened on one of the seats, tagged with the link generation
+/// so that events from a replaced connection can be told apart.
+#[derive(Debug)]
+pub struct LinkEvent {
+    pub player: Player,
+    pub generation: u32,
+    pub kind: LinkEventKind,
+}
+
+#[derive(Debug)]
+pub enum LinkEventKind {
+    Action(PlayerAction),
+    Left,
+    /// The seat gave up the game for good, like an external bot that crashed.
+    Forfeit,
+}
+
+/// Drive a player's socket on its own task. Messages pushed into the returned
+/// sender are forwarded to the client, and anything the client sends comes back
+/// through `events`. Dropping the sender closes the socket.
+///
+/// The link also pings the client every `HEARTBEAT_INTERVAL` and reports the seat
+/// as left once the client has been silent for longer than `IDLE_TIMEOUT`.
+pub fn spawn_socket_link(
+    mut connection: PlayerConnection,
+    player: Player,
+    generation: u32,
+    events: UnboundedSender<LinkEvent>,
+) -> UnboundedSender<ServerMessage> {
+    let (messages, mut outgoing) = mpsc::unbounded_channel::<ServerMessage>();
+
+    tokio::spawn(async move {
+        let mut heartbeat =
+            tokio::time::interval_at(Instant::now() + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+        let mut last_seen = Instant::now();
+
+        loop {
+            tokio::select! {
+                message = outgoing.recv() => {
+                    let Some(message) = message else {
+                        let close_frame = CloseFrame {
+                            code: CloseCode::Normal,
+                            reason: "".into(),
+                        };
+                        let _ = connection.close(Some(close_frame)).await;
+                        return;
+                    };
+                    let text = serde_json::to_string(&message).unwrap();
+                    if !send_with_timeout(&mut connection, Message::Text(text)).await {
+                        break;
+                    }
+                }
+                _ = heartbeat.tick() => {
+                    if last_seen.elapsed() > IDLE_TIMEOUT {
+                        println!("{player:?} timed out");
+                        break;
+                    }
+                    if !send_with_timeout(&mut connection, Message::Ping(Vec::new())).await {
+                        break;
+                    }
+                }
+                incoming = connection.next() => {
+                    last_seen = Instant::now();
+                    match incoming {
+                        Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                            Ok(action) => {
+                                let kind = LinkEventKind::Action(action);
+                                let _ = events.send(LinkEvent { player, generation, kind });
+                            }
+                            Err(e) => println!("Ignoring malformed action from {player:?}: {e}"),
+                        },
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                        // Pongs and anything else only count as a sign of life.
+                        Some(Ok(_)) => {}
+                    }
+                }
+            }
+        }
+
+        let kind = LinkEventKind::Left;
+        let _ = events.send(LinkEvent {
+            player,
+            generation,
+            kind,
+        });
+    });
+
+    messages
+}
+
+/// A send into a dead TCP connection can stall once the kernel buffer fills up, so
+/// give up on it after the idle timeout like we would for a silent client.
+async fn send_with_timeout(connection: &mut PlayerConnection, message: Message) -> bool {
+    matches!(
+        timeout(IDLE_TIMEOUT, connection.send(message)).await,
+        Ok(Ok(()))
+    )
+}
@@ -0,0 +1,71 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{game_logic::Player, PlayerConnection};
+
+const RESUME_TOKEN_LENGTH: usize = 24;
+
+/// A reconnecting client, handed to the session that owns its seat.
+pub type Resume = (Player, PlayerConnection);
+
+/// The seat a resume token unlocks and the session to hand the connection to.
+#[derive(Clone)]
+pub struct ResumeSeat {
+    pub player: Player,
+    pub session: UnboundedSender<Resume>,
+}
+
+/// Lets new connections find the running session they belong to: players coming
+/// back with a resume token, and spectators asking for a room by its code.
+#[derive(Clone, Default)]
+pub struct SessionRegistry {
+    resume_seats: Arc<Mutex<HashMap<String, ResumeSeat>>>,
+    spectatable_rooms: Arc<Mutex<HashMap<String, UnboundedSender<PlayerConnection>>>>,
+}
+
+impl SessionRegistry {
+    pub fn issue_resume_token(&self, player: Player, session: UnboundedSender<Resume>) -> String {
+        let token: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(RESUME_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        self.resume_seats
+            .lock()
+            .unwrap()
+            .insert(token.clone(), ResumeSeat { player, session });
+        token
+    }
+
+    pub fn resume_seat(&self, token: &str) -> Option<ResumeSeat> {
+        self.resume_seats.lock().unwrap().get(token).cloned()
+    }
+
+    pub fn revoke_resume_token(&self, token: &str) {
+        self.resume_seats.lock().unwrap().remove(token);
+    }
+
+    pub fn open_to_spectators(&self, code: &str, session: UnboundedSender<PlayerConnection>) {
+        self.spectatable_rooms
+            .lock()
+            .unwrap()
+            .insert(code.to_string(), session);
+    }
+
+    pub fn spectatable_room(&self, code: &str) -> Option<UnboundedSender<PlayerConnection>> {
+        self.spectatable_rooms
+            .lock()
+            .unwrap()
+            .get(&code.to_ascii_uppercase())
+            .cloned()
+    }
+
+    pub fn close_to_spectators(&self, code: &str) {
+        self.spectatable_rooms.lock().unwrap().remove(code);
+    }
+}
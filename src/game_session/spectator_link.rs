@@ -0,0 +1,63 @@
+use futures_util::{SinkExt, StreamExt};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_tungstenite::tungstenite::{
+    protocol::{frame::coding::CloseCode, CloseFrame},
+    Message,
+};
+
+use crate::{PlayerAction, PlayerConnection, SpectatorAction, SpectatorMessage};
+
+/// Drive a spectator's socket on its own task, starting from `first_message`.
+/// Spectators can't affect the game, so any `PlayerAction` they send is answered
+/// on the spot with `ActionRejected` and never reaches the session.
+pub fn spawn_spectator_link(
+    mut connection: PlayerConnection,
+    first_message: SpectatorMessage,
+) -> UnboundedSender<SpectatorMessage> {
+    let (messages, mut outgoing) = mpsc::unbounded_channel();
+    // Rejections repeat the latest view, which starts out as the first message's
+    // even if the spectator acts before it has gone out.
+    let mut spectator_view = first_message.spectator_view.clone();
+    let _ = messages.send(first_message);
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                message = outgoing.recv() => {
+                    let Some(message) = message else {
+                        let close_frame = CloseFrame {
+                            code: CloseCode::Normal,
+                            reason: "".into(),
+                        };
+                        let _ = connection.close(Some(close_frame)).await;
+                        return;
+                    };
+                    let text = serde_json::to_string(&message).unwrap();
+                    if connection.send(Message::Text(text)).await.is_err() {
+                        return;
+                    }
+                    spectator_view = message.spectator_view;
+                }
+                incoming = connection.next() => match incoming {
+                    Some(Ok(Message::Text(text))) => {
+                        if serde_json::from_str::<PlayerAction>(&text).is_err() {
+                            continue;
+                        }
+                        let rejection = SpectatorMessage {
+                            action: SpectatorAction::ActionRejected,
+                            spectator_view: spectator_view.clone(),
+                        };
+                        let text = serde_json::to_string(&rejection).unwrap();
+                        if connection.send(Message::Text(text)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+    });
+
+    messages
+}
@@ -0,0 +1,492 @@
+use std::{
+    path::Path,
+    sync::mpsc::{self, Sender},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::{
+    game_logic::Player,
+    game_session::{GameEnd, GameOutcome},
+    PlayerAction, RoomSettings,
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS games (
+        id INTEGER PRIMARY KEY,
+        seed INTEGER NOT NULL,
+        player1 TEXT NOT NULL,
+        player2 TEXT NOT NULL,
+        player3 TEXT,
+        player4 TEXT,
+        settings TEXT NOT NULL,
+        started_at INTEGER NOT NULL,
+        ended_at INTEGER,
+        winner TEXT,
+        end_reason TEXT
+    );
+    CREATE TABLE IF NOT EXISTS game_actions (
+        game_id INTEGER NOT NULL REFERENCES games(id),
+        move_index INTEGER NOT NULL,
+        at INTEGER NOT NULL,
+        player TEXT,
+        action TEXT NOT NULL,
+        PRIMARY KEY (game_id, move_index)
+    );
+";
+
+/// Columns added since the first schema, added to older databases when they are opened.
+const ADDED_COLUMNS: [(&str, &str); 2] = [("player3", "TEXT"), ("player4", "TEXT")];
+
+/// Where game history is stored unless `SPEED_DB_PATH` says otherwise.
+pub const DEFAULT_DB_PATH: &str = "speed-history.db";
+
+const GAME_COLUMNS: &str = "id, seed, settings, started_at, ended_at, winner, end_reason, \
+     player1, player2, player3, player4";
+
+/// Every game the server has played, with the moves applied in each, kept in
+/// an SQLite database. The connection lives on a thread of its own, so games
+/// hand their moves over without waiting for the disk, and queries see every
+/// write made before them.
+#[derive(Clone)]
+pub struct GameHistory {
+    jobs: Sender<Job>,
+}
+
+type Job = Box<dyn FnOnce(&Connection) + Send>;
+
+/// A stored game. Times are milliseconds since the Unix epoch, and the ending
+/// fields stay `None` until the game has finished.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    pub id: i64,
+    pub seed: u64,
+    /// Where each player connected from, indexed by `Player as usize`.
+    pub players: Vec<String>,
+    pub settings: RoomSettings,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub winner: Option<Player>,
+    /// The winner's partner in a team game, who won with them. Worked out from
+    /// the stored rules rather than stored.
+    pub partner: Option<Player>,
+    pub end: Option<GameEnd>,
+}
+
+/// A move the server applied to the table, as it was received.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LoggedAction {
+    /// Milliseconds since the Unix epoch.
+    pub at: i64,
+    /// `None` for the flip the server makes itself at the end of a stalemate countdown.
+    pub player: Option<Player>,
+    pub action: PlayerAction,
+}
+
+/// A stored game together with its moves, in the order they were applied.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameLog {
+    pub game: GameRecord,
+    pub actions: Vec<LoggedAction>,
+}
+
+impl GameHistory {
+    /// Open the database at `path`, creating it if it doesn't exist yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<GameHistory> {
+        let path = path.as_ref();
+        let connection = Connection::open(path)
+            .with_context(|| format!("could not open game history at {}", path.display()))?;
+        GameHistory::with_connection(connection)
+    }
+
+    /// Open the database named by `SPEED_DB_PATH`, or `DEFAULT_DB_PATH`.
+    pub fn open_configured() -> Result<GameHistory> {
+        let path = std::env::var("SPEED_DB_PATH").unwrap_or_else(|_| DEFAULT_DB_PATH.to_string());
+        GameHistory::open(path)
+    }
+
+    /// A history that only lasts as long as the process, for tests and throwaway servers.
+    pub fn open_in_memory() -> Result<GameHistory> {
+        GameHistory::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<GameHistory> {
+        connection.execute_batch(SCHEMA)?;
+        for (column, column_type) in ADDED_COLUMNS {
+            let exists: bool = connection.query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('games') WHERE name = ?1",
+                [column],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                connection.execute(
+                    &format!("ALTER TABLE games ADD COLUMN {column} {column_type}"),
+                    [],
+                )?;
+            }
+        }
+        let (jobs, queue) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("game-history".to_string())
+            .spawn(move || {
+                for job in queue {
+                    job(&connection);
+                }
+            })?;
+        Ok(GameHistory { jobs })
+    }
+
+    /// Run `query` on the database thread and wait for its result without
+    /// holding up the caller's runtime thread.
+    async fn query<T: Send + 'static>(
+        &self,
+        query: impl FnOnce(&Connection) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let (reply, result) = oneshot::channel();
+        self.jobs
+            .send(Box::new(move |connection| {
+                let _ = reply.send(query(connection));
+            }))
+            .map_err(|_| anyhow!("the game history has shut down"))?;
+        result.await.context("the game history has shut down")?
+    }
+
+    /// Queue `write` on the database thread without waiting for it. Failures
+    /// are logged there, since nobody is waiting to hear about them.
+    fn write(
+        &self,
+        failure: String,
+        write: impl FnOnce(&Connection) -> Result<()> + Send + 'static,
+    ) -> Result<()> {
+        self.jobs
+            .send(Box::new(move |connection| {
+                if let Err(e) = write(connection) {
+                    println!("{failure}: {e}");
+                }
+            }))
+            .map_err(|_| anyhow!("the game history has shut down"))
+    }
+
+    /// Store a game that is just starting and return a recorder for its moves.
+    /// `players` has a name for each seat, two to four of them.
+    pub async fn record_game(
+        &self,
+        seed: u64,
+        players: Vec<String>,
+        settings: &RoomSettings,
+    ) -> Result<GameRecorder> {
+        ensure!(players.len() >= 2, "a game needs two players");
+        let settings = serde_json::to_string(settings)?;
+        let game_id = self
+            .query(move |connection| {
+                let seat = |index: usize| players.get(index);
+                connection.execute(
+                "INSERT INTO games (seed, player1, player2, player3, player4, settings, started_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    // SQLite only has signed integers; the bits round-trip unchanged.
+                    seed as i64,
+                    seat(0),
+                    seat(1),
+                    seat(2),
+                    seat(3),
+                    settings,
+                    now(),
+                ],
+            )?;
+                Ok(connection.last_insert_rowid())
+            })
+            .await?;
+        Ok(GameRecorder {
+            history: self.clone(),
+            game_id,
+            actions_logged: 0,
+        })
+    }
+
+    /// All stored games, oldest first.
+    pub async fn list_games(&self) -> Result<Vec<GameRecord>> {
+        self.query(|connection| {
+            let mut statement =
+                connection.prepare(&format!("SELECT {GAME_COLUMNS} FROM games ORDER BY id"))?;
+            let rows = statement.query_map([], game_record)?;
+            Ok(rows.collect::<rusqlite::Result<_>>()?)
+        })
+        .await
+    }
+
+    /// The game with the given id and every move applied in it, if it exists.
+    pub async fn game_log(&self, id: i64) -> Result<Option<GameLog>> {
+        self.query(move |connection| game_log(connection, id)).await
+    }
+}
+
+/// The body of `GameHistory::game_log`, run on the database thread.
+fn game_log(connection: &Connection, id: i64) -> Result<Option<GameLog>> {
+    let Some(game) = connection
+        .query_row(
+            &format!("SELECT {GAME_COLUMNS} FROM games WHERE id = ?1"),
+            [id],
+            game_record,
+        )
+        .optional()?
+    else {
+        return Ok(None);
+    };
+
+    let mut statement = connection.prepare(
+        "SELECT at, player, action FROM game_actions WHERE game_id = ?1 ORDER BY move_index",
+    )?;
+    let actions = statement
+        .query_map([id], |row| {
+            Ok(LoggedAction {
+                at: row.get(0)?,
+                player: optional_column(row, 1)?,
+                action: json_column(row, 2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(Some(GameLog { game, actions }))
+}
+
+/// Appends the moves of one running game to its stored log.
+pub struct GameRecorder {
+    history: GameHistory,
+    game_id: i64,
+    actions_logged: u32,
+}
+
+impl GameRecorder {
+    pub fn game_id(&self) -> i64 {
+        self.game_id
+    }
+
+    pub fn record_action(&mut self, player: Option<Player>, action: PlayerAction) -> Result<()> {
+        let player = player.map(|player| enum_text(&player)).transpose()?;
+        let action = serde_json::to_string(&action)?;
+        let (game_id, move_index, at) = (self.game_id, self.actions_logged, now());
+        self.actions_logged += 1;
+        self.history.write(
+            format!("Could not record a move in game {game_id}"),
+            move |connection| {
+                connection.execute(
+                    "INSERT INTO game_actions (game_id, move_index, at, player, action)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![game_id, move_index, at, player, action],
+                )?;
+                Ok(())
+            },
+        )
+    }
+
+    pub fn record_outcome(&self, outcome: &GameOutcome) -> Result<()> {
+        let winner = enum_text(&outcome.winner)?;
+        let end = enum_text(&outcome.end)?;
+        let (game_id, at) = (self.game_id, now());
+        self.history.write(
+            format!("Could not record the end of game {game_id}"),
+            move |connection| {
+                connection.execute(
+                    "UPDATE games SET ended_at = ?1, winner = ?2, end_reason = ?3 WHERE id = ?4",
+                    params![at, winner, end, game_id],
+                )?;
+                Ok(())
+            },
+        )
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+fn game_record(row: &Row) -> rusqlite::Result<GameRecord> {
+    let settings: RoomSettings = json_column(row, 2)?;
+    let winner: Option<Player> = optional_column(row, 5)?;
+    Ok(GameRecord {
+        id: row.get(0)?,
+        seed: row.get::<_, i64>(1)? as u64,
+        started_at: row.get(3)?,
+        ended_at: row.get(4)?,
+        winner,
+        partner: winner.and_then(|winner| settings.rules.partner(winner)),
+        settings,
+        end: optional_column(row, 6)?,
+        players: (7..11)
+            .map(|index| row.get::<_, Option<String>>(index))
+            .filter_map(Result::transpose)
+            .collect::<rusqlite::Result<_>>()?,
+    })
+}
+
+/// Unit enum variants are stored by name, e.g. `PLAYER1` rather than `"PLAYER1"`.
+fn enum_text<T: Serialize>(value: &T) -> Result<String> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(text) => Ok(text),
+        other => Ok(other.to_string()),
+    }
+}
+
+fn optional_column<T: DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<Option<T>> {
+    row.get::<_, Option<String>>(index)?
+        .map(|text| {
+            serde_json::from_value(serde_json::Value::String(text))
+                .map_err(|e| conversion_error(index, e))
+        })
+        .transpose()
+}
+
+fn json_column<T: DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let text: String = row.get(index)?;
+    serde_json::from_str(&text).map_err(|e| conversion_error(index, e))
+}
+
+fn conversion_error(index: usize, error: serde_json::Error) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        game_logic::{RulesConfig, Side, Variant},
+        FlipMode,
+    };
+
+    #[tokio::test]
+    async fn test_record_and_fetch_game() {
+        let history = GameHistory::open_in_memory().unwrap();
+        let settings = RoomSettings {
+            flip_mode: FlipMode::Both,
+            ..RoomSettings::default()
+        };
+        let players = vec!["127.0.0.1:5000".to_string(), "127.0.0.1:5001".to_string()];
+
+        let mut recorder = history
+            .record_game(u64::MAX, players.clone(), &settings)
+            .await
+            .unwrap();
+        let moves = [
+            (Some(Player::PLAYER1), PlayerAction::DrawCard),
+            (None, PlayerAction::Flip),
+            (
+                Some(Player::PLAYER2),
+                PlayerAction::PlaceCard(3, Side::RIGHT),
+            ),
+        ];
+        for (player, action) in moves {
+            recorder.record_action(player, action).unwrap();
+        }
+
+        let unfinished = &history.list_games().await.unwrap()[0];
+        assert_eq!(unfinished.winner, None);
+        assert_eq!(unfinished.ended_at, None);
+
+        recorder
+            .record_outcome(&GameOutcome {
+                seed: u64::MAX,
+                winner: Player::PLAYER2,
+                partner: None,
+                end: GameEnd::Forfeit,
+                duration: Duration::from_secs(1),
+                cards_played: vec![0, 1],
+                middle_flips: 1,
+            })
+            .unwrap();
+
+        let log = history.game_log(recorder.game_id()).await.unwrap().unwrap();
+        assert_eq!(log.game.seed, u64::MAX);
+        assert_eq!(log.game.players, players);
+        assert_eq!(log.game.settings, settings);
+        assert_eq!(log.game.winner, Some(Player::PLAYER2));
+        assert_eq!(log.game.end, Some(GameEnd::Forfeit));
+        assert!(log.game.ended_at.unwrap() >= log.game.started_at);
+        assert_eq!(
+            log.actions
+                .iter()
+                .map(|logged| (logged.player, logged.action))
+                .collect::<Vec<_>>(),
+            moves
+        );
+        assert!(log.actions.windows(2).all(|pair| pair[0].at <= pair[1].at));
+
+        assert_eq!(history.list_games().await.unwrap(), vec![log.game]);
+        assert_eq!(
+            history.game_log(recorder.game_id() + 1).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bigger_tables_and_old_databases() {
+        let connection = Connection::open_in_memory().unwrap();
+        // The schema from before tables could seat more than two players.
+        connection
+            .execute_batch(
+                "CREATE TABLE games (
+                    id INTEGER PRIMARY KEY,
+                    seed INTEGER NOT NULL,
+                    player1 TEXT NOT NULL,
+                    player2 TEXT NOT NULL,
+                    settings TEXT NOT NULL,
+                    started_at INTEGER NOT NULL,
+                    ended_at INTEGER,
+                    winner TEXT,
+                    end_reason TEXT
+                );
+                INSERT INTO games (seed, player1, player2, settings, started_at)
+                VALUES (1, 'a', 'b', '{}', 0);",
+            )
+            .unwrap();
+        let history = GameHistory::with_connection(connection).unwrap();
+        assert_eq!(history.list_games().await.unwrap()[0].players, ["a", "b"]);
+
+        let players: Vec<String> = ["a", "b", "c"].map(String::from).to_vec();
+        let recorder = history
+            .record_game(2, players.clone(), &RoomSettings::default())
+            .await
+            .unwrap();
+        let log = history.game_log(recorder.game_id()).await.unwrap().unwrap();
+        assert_eq!(log.game.players, players);
+    }
+
+    #[tokio::test]
+    async fn test_team_winners() {
+        let history = GameHistory::open_in_memory().unwrap();
+        let settings = RoomSettings {
+            rules: RulesConfig {
+                teams: true,
+                ..RulesConfig::for_players(Variant::House, 4)
+            },
+            ..RoomSettings::default()
+        };
+        let players: Vec<String> = ["a", "b", "c", "d"].map(String::from).to_vec();
+        let recorder = history.record_game(3, players, &settings).await.unwrap();
+        recorder
+            .record_outcome(&GameOutcome {
+                seed: 3,
+                winner: Player::PLAYER4,
+                partner: Some(Player::PLAYER2),
+                end: GameEnd::Won,
+                duration: Duration::from_secs(1),
+                cards_played: vec![0; 4],
+                middle_flips: 0,
+            })
+            .unwrap();
+
+        let game = &history.list_games().await.unwrap()[0];
+        assert_eq!(game.winner, Some(Player::PLAYER4));
+        assert_eq!(game.partner, Some(Player::PLAYER2));
+    }
+}
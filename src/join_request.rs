@@ -0,0 +1,174 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{bot::BotLevel, game_logic::RulesConfig};
+
+/// First message a client sends after connecting, choosing how it wants to be seated.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(remote = "Self")]
+pub enum JoinRequest {
+    QuickMatch,
+    /// Open a private room. The plain `"CreateRoom"` opens one with the default settings.
+    CreateRoom(RoomSettings),
+    JoinRoom(String),
+    /// Watch the game being played in a private room without taking a seat.
+    Spectate(String),
+    /// Reclaim a seat in a running game with the token from its `SetBoard` message.
+    Resume(String),
+    /// Watch a finished game from the history play back, by its id.
+    Replay(i64),
+    /// Play right away against a bot run by the server.
+    Bot(BotLevel),
+}
+
+impl JoinRequest {
+    /// The request a client makes just by connecting to `path`, for clients that
+    /// don't send a `JoinRequest` message: `/` for a quick match, `/room` to open
+    /// a room with the default settings, `/room/<code>`, `/spectate/<code>`,
+    /// `/resume/<token>`, `/replay/<game id>` and `/bot/easy`, `/bot/medium` or
+    /// `/bot/hard`. `None` for any other path.
+    pub fn from_path(path: &str) -> Option<JoinRequest> {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        Some(match segments.as_slice() {
+            [""] => JoinRequest::QuickMatch,
+            ["room"] => JoinRequest::CreateRoom(RoomSettings::default()),
+            ["room", code] => JoinRequest::JoinRoom(code.to_string()),
+            ["spectate", code] => JoinRequest::Spectate(code.to_string()),
+            ["resume", token] => JoinRequest::Resume(token.to_string()),
+            ["replay", game_id] => JoinRequest::Replay(game_id.parse().ok()?),
+            ["bot", "easy"] => JoinRequest::Bot(BotLevel::Easy),
+            ["bot", "medium"] => JoinRequest::Bot(BotLevel::Medium),
+            ["bot", "hard"] => JoinRequest::Bot(BotLevel::Hard),
+            _ => return None,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for JoinRequest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// `"CreateRoom"` was a unit variant before rooms took settings.
+        #[derive(Deserialize)]
+        enum UnitCreateRoom {
+            CreateRoom,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Message {
+            Request(#[serde(with = "JoinRequest")] JoinRequest),
+            UnitCreateRoom(UnitCreateRoom),
+        }
+
+        Ok(match Message::deserialize(deserializer)? {
+            Message::Request(request) => request,
+            Message::UnitCreateRoom(UnitCreateRoom::CreateRoom) => {
+                JoinRequest::CreateRoom(RoomSettings::default())
+            }
+        })
+    }
+}
+
+impl Serialize for JoinRequest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        JoinRequest::serialize(self, serializer)
+    }
+}
+
+/// Options picked by whoever creates a private room. Quick matches use the defaults.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct RoomSettings {
+    pub reveal_hands_to_spectators: bool,
+    pub flip_mode: FlipMode,
+    pub rules: RulesConfig,
+}
+
+/// Who gets to flip the middle cards when both players are stuck.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum FlipMode {
+    /// Both players send `Flip` and the cards flip once both have voted.
+    #[default]
+    Manual,
+    /// The server spots the stalemate and flips after a short countdown; `Flip` is rejected.
+    Automatic,
+    /// The server flips on a stalemate, and players may still agree to flip earlier.
+    Both,
+}
+
+impl FlipMode {
+    pub fn allows_manual_flip(&self) -> bool {
+        matches!(self, FlipMode::Manual | FlipMode::Both)
+    }
+
+    pub fn allows_automatic_flip(&self) -> bool {
+        matches!(self, FlipMode::Automatic | FlipMode::Both)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde() {
+        assert_eq!(
+            serde_json::from_str::<JoinRequest>("\"QuickMatch\"").unwrap(),
+            JoinRequest::QuickMatch
+        );
+        assert_eq!(
+            serde_json::from_str::<JoinRequest>("{\"JoinRoom\":\"K7QXM\"}").unwrap(),
+            JoinRequest::JoinRoom("K7QXM".to_string())
+        );
+        assert_eq!(
+            serde_json::from_str::<JoinRequest>("{\"CreateRoom\":{}}").unwrap(),
+            JoinRequest::CreateRoom(RoomSettings::default())
+        );
+        assert!(serde_json::from_str::<JoinRequest>("\"JoinRoom\"").is_err());
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(JoinRequest::from_path("/"), Some(JoinRequest::QuickMatch));
+        assert_eq!(JoinRequest::from_path(""), Some(JoinRequest::QuickMatch));
+        assert_eq!(
+            JoinRequest::from_path("/room"),
+            Some(JoinRequest::CreateRoom(RoomSettings::default()))
+        );
+        assert_eq!(
+            JoinRequest::from_path("/room/K7QXM/"),
+            Some(JoinRequest::JoinRoom("K7QXM".to_string()))
+        );
+        assert_eq!(
+            JoinRequest::from_path("/replay/12"),
+            Some(JoinRequest::Replay(12))
+        );
+        assert_eq!(
+            JoinRequest::from_path("/bot/hard"),
+            Some(JoinRequest::Bot(BotLevel::Hard))
+        );
+        for path in [
+            "/replay/twelve",
+            "/bot/impossible",
+            "/room/K7QXM/extra",
+            "/join",
+        ] {
+            assert_eq!(JoinRequest::from_path(path), None, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_unit_create_room() {
+        assert_eq!(
+            serde_json::from_str::<JoinRequest>("\"CreateRoom\"").unwrap(),
+            JoinRequest::CreateRoom(RoomSettings::default())
+        );
+        let settings = RoomSettings {
+            reveal_hands_to_spectators: true,
+            ..RoomSettings::default()
+        };
+        let json = serde_json::to_string(&JoinRequest::CreateRoom(settings.clone())).unwrap();
+        assert_eq!(
+            serde_json::from_str::<JoinRequest>(&json).unwrap(),
+            JoinRequest::CreateRoom(settings)
+        );
+    }
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Replies to a `JoinRequest`, sent before the game itself starts.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub enum JoinResponse {
+    RoomCreated(String),
+    /// Seated in a room that is still waiting for players: how many are
+    /// seated so far, out of the `players` it needs.
+    RoomJoined {
+        seated: usize,
+        players: usize,
+    },
+    /// Someone left the room while it was still waiting, sent to everyone
+    /// who is still there: how many are left, out of the `players` it needs.
+    RoomLeft {
+        seated: usize,
+        players: usize,
+    },
+    RoomNotFound,
+    RoomExpired,
+    ResumeFailed,
+    InvalidRequest,
+    ReplayNotFound,
+    /// The room's `RulesConfig` can't be dealt, and why.
+    InvalidRules(String),
+}
@@ -0,0 +1,74 @@
+pub mod game_logic;
+mod player_action;
+pub use player_action::*;
+mod server_message;
+pub use server_message::*;
+mod join_request;
+pub use join_request::*;
+mod join_response;
+pub use join_response::*;
+mod spectator_message;
+pub use spectator_message::*;
+mod replay_message;
+pub use replay_message::*;
+pub mod bot;
+pub mod game_session;
+pub mod history;
+pub mod replay;
+pub mod simulation;
+use history::GameHistory;
+mod lobby;
+use lobby::Lobby;
+
+use anyhow::Result;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{
+    tungstenite::handshake::server::{Request, Response},
+    WebSocketStream,
+};
+
+pub type PlayerConnection = WebSocketStream<TcpStream>;
+
+/// Accept connections on `address` and admit each one to the lobby, forever.
+/// Every game played is stored in `history`.
+pub async fn start_server(address: &str, history: GameHistory) -> Result<()> {
+    serve(TcpListener::bind(address).await?, history).await
+}
+
+/// Like `start_server`, on a listener that is already bound.
+pub async fn serve(listener: TcpListener, history: GameHistory) -> Result<()> {
+    let lobby = Lobby::start(history);
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let lobby = lobby.clone();
+
+        // The WebSocket handshake runs on its own task so a slow client can't
+        // hold up the accept loop.
+        tokio::spawn(async move {
+            match connect_player(stream).await {
+                Ok((player, path)) => {
+                    println!("Player connected from {peer}");
+                    if let Err(e) = lobby.admit(player, &path).await {
+                        println!("Could not admit {peer}: {e}");
+                    }
+                }
+                Err(e) => println!("Handshake with {peer} failed: {e}"),
+            }
+        });
+    }
+}
+
+/// Complete the WebSocket handshake, keeping the path the client asked for.
+// The handshake callback's error type is tungstenite's, not ours to shrink.
+#[allow(clippy::result_large_err)]
+async fn connect_player(stream: TcpStream) -> Result<(PlayerConnection, String)> {
+    let mut path = String::new();
+    let player_stream =
+        tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+            path = request.uri().path().to_string();
+            Ok(response)
+        })
+        .await?;
+    Ok((player_stream, path))
+}
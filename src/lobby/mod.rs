@@ -0,0 +1,230 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use futures_util::{SinkExt, StreamExt};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    game_session::{self, Participant, SessionRegistry},
+    history::GameHistory,
+    replay::{self, ReplayTimeline},
+    JoinRequest, JoinResponse, PlayerConnection, RoomSettings,
+};
+
+mod rooms;
+use rooms::Rooms;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Clients that connect here send a `JoinRequest` as their first message;
+/// every other path is a request of its own (see `JoinRequest::from_path`).
+const JOIN_PATH: &str = "/join";
+const ROOM_EXPIRY: Duration = Duration::from_secs(10 * 60);
+
+/// Handle to the matchmaking queue and the private rooms. Every new connection
+/// is admitted through here and ends up in a game running on its own tokio task.
+#[derive(Clone)]
+pub struct Lobby {
+    queue: UnboundedSender<PlayerConnection>,
+    rooms: Rooms,
+    registry: SessionRegistry,
+    history: GameHistory,
+}
+
+impl Lobby {
+    pub fn start(history: GameHistory) -> Lobby {
+        let (queue, waiting_players) = mpsc::unbounded_channel();
+        let registry = SessionRegistry::default();
+        tokio::spawn(run_matchmaker(
+            waiting_players,
+            registry.clone(),
+            history.clone(),
+        ));
+        Lobby {
+            queue,
+            rooms: Rooms::new(ROOM_EXPIRY, registry.clone(), history.clone()),
+            registry,
+            history,
+        }
+    }
+
+    /// Work out what the player asked for, from the path they connected to or
+    /// the `JoinRequest` they send on `JOIN_PATH`, and seat them accordingly.
+    pub async fn admit(&self, mut player: PlayerConnection, path: &str) -> Result<()> {
+        let request = if path == JOIN_PATH {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, read_join_request(&mut player)).await {
+                Ok(Ok(request)) => request,
+                Ok(Err(e)) => {
+                    let _ = send_join_response(&mut player, JoinResponse::InvalidRequest).await;
+                    return Err(e);
+                }
+                Err(_) => bail!("no join request received"),
+            }
+        } else if let Some(request) = JoinRequest::from_path(path) {
+            request
+        } else {
+            send_join_response(&mut player, JoinResponse::InvalidRequest).await?;
+            player.close(None).await?;
+            bail!("no such path {path}");
+        };
+
+        match request {
+            JoinRequest::QuickMatch => self.enqueue(player),
+            JoinRequest::CreateRoom(settings) => match settings.rules.validate() {
+                Ok(()) => self.rooms.create(player, settings).await?,
+                Err(e) => {
+                    let response = JoinResponse::InvalidRules(e.to_string());
+                    send_join_response(&mut player, response).await?;
+                    player.close(None).await?;
+                }
+            },
+            JoinRequest::JoinRoom(code) => {
+                if let Some(mut player) = self.rooms.join(&code, player) {
+                    send_join_response(&mut player, JoinResponse::RoomNotFound).await?;
+                    player.close(None).await?;
+                }
+            }
+            JoinRequest::Spectate(code) => {
+                let rejected = match self.registry.spectatable_room(&code) {
+                    Some(session) => session.send(player).err().map(|e| e.0),
+                    None => Some(player),
+                };
+                if let Some(mut player) = rejected {
+                    send_join_response(&mut player, JoinResponse::RoomNotFound).await?;
+                    player.close(None).await?;
+                }
+            }
+            JoinRequest::Resume(token) => {
+                // The session may have finished since the token was looked up, in
+                // which case the connection comes back with the failed send.
+                let rejected = match self.registry.resume_seat(&token) {
+                    Some(seat) => seat
+                        .session
+                        .send((seat.player, player))
+                        .err()
+                        .map(|e| e.0 .1),
+                    None => Some(player),
+                };
+                if let Some(mut player) = rejected {
+                    send_join_response(&mut player, JoinResponse::ResumeFailed).await?;
+                    player.close(None).await?;
+                }
+            }
+            JoinRequest::Bot(level) => spawn_game(
+                vec![
+                    Participant::Remote(player),
+                    Participant::Bot(level.strategy()),
+                ],
+                None,
+                RoomSettings::default(),
+                self.registry.clone(),
+                self.history.clone(),
+            ),
+            JoinRequest::Replay(game_id) => match self.load_replay(game_id).await {
+                Ok(Some(timeline)) => {
+                    tokio::spawn(async move {
+                        if let Err(e) = replay::play_back(player, timeline).await {
+                            println!("Replay of game {game_id} ended with error: {e}");
+                        }
+                    });
+                }
+                result => {
+                    send_join_response(&mut player, JoinResponse::ReplayNotFound).await?;
+                    player.close(None).await?;
+                    result?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    async fn load_replay(&self, game_id: i64) -> Result<Option<ReplayTimeline>> {
+        match self.history.game_log(game_id).await? {
+            Some(log) => Ok(Some(ReplayTimeline::from_log(&log)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn enqueue(&self, player: PlayerConnection) {
+        // The matchmaker only stops once every lobby handle is dropped.
+        let _ = self.queue.send(player);
+    }
+}
+
+async fn read_join_request(player: &mut PlayerConnection) -> Result<JoinRequest> {
+    loop {
+        match player.next().await {
+            Some(Ok(Message::Text(text))) => return Ok(serde_json::from_str(&text)?),
+            Some(Ok(Message::Close(_))) | None => bail!("connection closed before joining"),
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(e.into()),
+        }
+    }
+}
+
+pub async fn send_join_response(
+    player: &mut PlayerConnection,
+    response: JoinResponse,
+) -> Result<()> {
+    player
+        .send(Message::Text(serde_json::to_string(&response)?))
+        .await?;
+    Ok(())
+}
+
+fn spawn_game(
+    participants: Vec<Participant>,
+    room_code: Option<String>,
+    settings: RoomSettings,
+    registry: SessionRegistry,
+    history: GameHistory,
+) {
+    tokio::spawn(async move {
+        match game_session::start_game(participants, room_code, settings, registry, history).await {
+            Ok(outcome) => println!("Game finished: {outcome:?}"),
+            Err(e) => println!("Game ended with error: {e}"),
+        }
+    });
+}
+
+async fn run_matchmaker(
+    mut waiting_players: UnboundedReceiver<PlayerConnection>,
+    registry: SessionRegistry,
+    history: GameHistory,
+) {
+    let mut waiting: Option<PlayerConnection> = None;
+
+    loop {
+        let Some(mut p1) = waiting.take() else {
+            match waiting_players.recv().await {
+                Some(player) => waiting = Some(player),
+                None => return,
+            }
+            continue;
+        };
+
+        // Keep reading from the waiting player so that a client which leaves the
+        // queue is dropped instead of being paired with the next arrival.
+        tokio::select! {
+            next = waiting_players.recv() => {
+                let Some(p2) = next else { return };
+                spawn_game(
+                    vec![Participant::Remote(p1), Participant::Remote(p2)],
+                    None,
+                    RoomSettings::default(),
+                    registry.clone(),
+                    history.clone(),
+                );
+            }
+            message = p1.next() => {
+                match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                        println!("Waiting player left the queue");
+                    }
+                    Some(Ok(_)) => waiting = Some(p1),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use futures_util::{future::select_all, StreamExt};
+use rand::{seq::SliceRandom, thread_rng};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::{send_join_response, spawn_game};
+use crate::{
+    game_session::{Participant, SessionRegistry},
+    history::GameHistory,
+    JoinResponse, PlayerConnection, RoomSettings,
+};
+
+const ROOM_CODE_LENGTH: usize = 5;
+// Letters and digits that are easy to tell apart when read out loud.
+const ROOM_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Private rooms waiting for the rest of their players, keyed by room code. Each
+/// open room runs on a task of its own, which holds the waiting players and
+/// takes new ones from the channel stored here.
+#[derive(Clone)]
+pub struct Rooms {
+    open_rooms: Arc<Mutex<HashMap<String, UnboundedSender<PlayerConnection>>>>,
+    expiry: Duration,
+    registry: SessionRegistry,
+    history: GameHistory,
+}
+
+impl Rooms {
+    pub fn new(expiry: Duration, registry: SessionRegistry, history: GameHistory) -> Rooms {
+        Rooms {
+            open_rooms: Arc::new(Mutex::new(HashMap::new())),
+            expiry,
+            registry,
+            history,
+        }
+    }
+
+    /// Open a room for `host`, who is told the code to share. The room then waits
+    /// until every seat is taken and starts the game, or expires.
+    pub async fn create(&self, mut host: PlayerConnection, settings: RoomSettings) -> Result<()> {
+        let code = self.unused_code();
+        send_join_response(&mut host, JoinResponse::RoomCreated(code.clone())).await?;
+
+        let (joins, joiners) = mpsc::unbounded_channel();
+        self.open_rooms.lock().unwrap().insert(code.clone(), joins);
+        tokio::spawn(self.clone().run_room(code, settings, host, joiners));
+        Ok(())
+    }
+
+    /// Hand `player` to the room with the given code. They come back if no room
+    /// with that code is open.
+    pub fn join(&self, code: &str, player: PlayerConnection) -> Option<PlayerConnection> {
+        let open_rooms = self.open_rooms.lock().unwrap();
+        match open_rooms.get(&code.to_ascii_uppercase()) {
+            // Rooms are only closed under this lock, so a listed room takes the player.
+            Some(joins) => joins.send(player).err().map(|e| e.0),
+            None => Some(player),
+        }
+    }
+
+    /// Keep reading from everyone waiting in the room, so that players who leave
+    /// are dropped instead of being dealt into the game.
+    async fn run_room(
+        self,
+        code: String,
+        settings: RoomSettings,
+        host: PlayerConnection,
+        mut joiners: UnboundedReceiver<PlayerConnection>,
+    ) {
+        let seats = settings.rules.players;
+        let mut players = vec![host];
+        let expiry = tokio::time::sleep(self.expiry);
+        tokio::pin!(expiry);
+
+        loop {
+            tokio::select! {
+                () = &mut expiry => {
+                    println!("Room {code} expired");
+                    let late = self.close(&code, &mut joiners);
+                    for mut player in players.into_iter().chain(late) {
+                        let _ = send_join_response(&mut player, JoinResponse::RoomExpired).await;
+                        let _ = player.close(None).await;
+                    }
+                    return;
+                }
+                Some(player) = joiners.recv() => {
+                    players.push(player);
+                    if players.len() < seats {
+                        let response = JoinResponse::RoomJoined {
+                            seated: players.len(),
+                            players: seats,
+                        };
+                        // A guest who has already gone is noticed by the reads below.
+                        let _ = send_join_response(players.last_mut().unwrap(), response).await;
+                        continue;
+                    }
+
+                    let late = self.close(&code, &mut joiners);
+                    reject(late).await;
+                    spawn_game(
+                        players.into_iter().map(Participant::Remote).collect(),
+                        Some(code),
+                        settings,
+                        self.registry.clone(),
+                        self.history.clone(),
+                    );
+                    return;
+                }
+                (message, index, _) = select_all(players.iter_mut().map(|player| player.next())) => {
+                    let seated = players.len();
+                    match message {
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                            println!("A player left room {code}");
+                            players.remove(index);
+                        }
+                        Some(Ok(_)) => {}
+                    }
+                    if players.is_empty() {
+                        let late = self.close(&code, &mut joiners);
+                        reject(late).await;
+                        return;
+                    }
+                    if players.len() < seated {
+                        let seated = players.len();
+                        for player in &mut players {
+                            let response = JoinResponse::RoomLeft { seated, players: seats };
+                            let _ = send_join_response(player, response).await;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Take the room off the list, returning anyone whose join was still queued.
+    fn close(
+        &self,
+        code: &str,
+        joiners: &mut UnboundedReceiver<PlayerConnection>,
+    ) -> Vec<PlayerConnection> {
+        self.open_rooms.lock().unwrap().remove(code);
+        joiners.close();
+        let mut late = Vec::new();
+        while let Ok(player) = joiners.try_recv() {
+            late.push(player);
+        }
+        late
+    }
+
+    fn unused_code(&self) -> String {
+        let open_rooms = self.open_rooms.lock().unwrap();
+        let mut rng = thread_rng();
+        loop {
+            let code: String = (0..ROOM_CODE_LENGTH)
+                .map(|_| *ROOM_CODE_ALPHABET.choose(&mut rng).unwrap() as char)
+                .collect();
+            // Games keep their room code while they run, so spectators can find them.
+            if !open_rooms.contains_key(&code) && self.registry.spectatable_room(&code).is_none() {
+                return code;
+            }
+        }
+    }
+}
+
+/// Turn away players who tried to join a room just as it closed.
+async fn reject(players: Vec<PlayerConnection>) {
+    for mut player in players {
+        let _ = send_join_response(&mut player, JoinResponse::RoomNotFound).await;
+        let _ = player.close(None).await;
+    }
+}
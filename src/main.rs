@@ -1,75 +1,530 @@
-mod game_logic;
-mod player_action;
-use player_action::*;
-mod server_message;
-use server_message::*;
-mod game_session;
-
 use anyhow::Result;
-use tokio::net::{TcpListener, TcpStream};
-use tokio_tungstenite::WebSocketStream;
-
-type PlayerConnection = WebSocketStream<TcpStream>;
+use speed_card_ws::{history::GameHistory, start_server};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    start_server().await
+    start_server("0.0.0.0:8080", GameHistory::open_configured()?).await
 }
 
-async fn start_server() -> Result<()> {
-    let listener = TcpListener::bind(&"0.0.0.0:8080".to_string()).await?;
+#[cfg(test)]
+mod tests {
+    use futures_util::{SinkExt, StreamExt};
+    use speed_card_ws::game_logic::{Player, Side, SpeedTable};
+    use speed_card_ws::*;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::{connect_async, tungstenite::Message, WebSocketStream};
 
-    let p1 = connect_player(&listener).await?;
-    println!("Player 1 connected!");
-    let p2 = connect_player(&listener).await?;
-    println!("Player 2 connected!");
+    use super::*;
 
-    game_session::start_game(p1, p2).await?;
+    type ClientConnection = WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>;
 
-    Ok(())
-}
+    /// Serve on a free local port, returning the URL to connect to. The port is
+    /// bound before this returns, so clients can connect straight away.
+    async fn spawn_test_server(history: GameHistory) -> Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("ws://{}", listener.local_addr()?);
+        tokio::spawn(serve(listener, history));
+        Ok(url)
+    }
 
-async fn connect_player(listener: &TcpListener) -> Result<PlayerConnection> {
-    let (stream, _) = listener.accept().await?;
-    let player_stream = tokio_tungstenite::accept_async(stream).await?;
-    Ok(player_stream)
-}
+    /// Connect the way clients that send a `JoinRequest` do, and send it.
+    async fn join(server: &str, request: JoinRequest) -> Result<ClientConnection> {
+        let (mut player, _) = connect_async(url::Url::parse(&format!("{server}/join"))?).await?;
+        player
+            .send(Message::Text(serde_json::to_string(&request)?))
+            .await?;
+        Ok(player)
+    }
 
-#[cfg(test)]
-mod tests {
-    use futures_util::StreamExt;
-    use game_logic::{Player, SpeedTable};
-    use std::{thread, time::Duration};
-    use tokio_tungstenite::connect_async;
+    /// Open a private room with `settings`, returning the host's connection and
+    /// the room's code.
+    async fn create_room(
+        server: &str,
+        settings: RoomSettings,
+    ) -> Result<(ClientConnection, String)> {
+        let mut host = join(server, JoinRequest::CreateRoom(settings)).await?;
+        let JoinResponse::RoomCreated(code) = read_message(&mut host).await? else {
+            panic!("expected a room code");
+        };
+        Ok((host, code))
+    }
 
-    use super::*;
+    async fn read_message<T: serde::de::DeserializeOwned>(
+        player: &mut ClientConnection,
+    ) -> Result<T> {
+        loop {
+            // Skip over heartbeat pings, the client library answers them for us.
+            if let Message::Text(text) = player.next().await.unwrap()? {
+                return Ok(serde_json::from_str(&text)?);
+            }
+        }
+    }
 
     #[tokio::test]
     async fn test_websocket_server_game() -> Result<()> {
-        std::thread::spawn(|| {
-            let _ = main();
-        });
-        thread::sleep(Duration::from_secs(2));
+        let server = spawn_test_server(GameHistory::open_in_memory()?).await?;
 
-        let (mut p1, _) = connect_async(url::Url::parse("ws://0.0.0.0:8080")?).await?;
-        let (mut p2, _) = connect_async(url::Url::parse("ws://0.0.0.0:8080")?).await?;
+        let (mut p1, _) = connect_async(url::Url::parse(&server)?).await?;
+        let (mut p2, _) = connect_async(url::Url::parse(&server)?).await?;
         let table = SpeedTable::new();
 
         let message1 = p1.next().await.unwrap()?.into_text()?;
         let message2 = p2.next().await.unwrap()?.into_text()?;
 
-        let empty_board_message = ServerMessage {
-            player_view: table.get_player_view(Player::PLAYER1),
+        // Either connection may be seated first; the views only differ in who
+        // the opponent is.
+        let empty_board_messages = [Player::PLAYER1, Player::PLAYER2].map(|player| ServerMessage {
+            player_view: table.get_player_view(player),
             action: ServerAction::SetBoard,
+            resume_token: None,
+        });
+        let mut message1: ServerMessage = serde_json::from_str(&message1).unwrap();
+        let mut message2: ServerMessage = serde_json::from_str(&message2).unwrap();
+        assert!(message1.resume_token.take().is_some());
+        assert!(message2.resume_token.take().is_some());
+        assert!(empty_board_messages.contains(&message1));
+        assert!(empty_board_messages.contains(&message2));
+        assert_ne!(message1, message2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_join_by_path() -> Result<()> {
+        let server = spawn_test_server(GameHistory::open_in_memory()?).await?;
+
+        let (mut lost, _) = connect_async(url::Url::parse(&format!("{server}/nowhere"))?).await?;
+        let response: JoinResponse = read_message(&mut lost).await?;
+        assert_eq!(response, JoinResponse::InvalidRequest);
+
+        let (mut host, _) = connect_async(url::Url::parse(&format!("{server}/room"))?).await?;
+        let JoinResponse::RoomCreated(code) = read_message(&mut host).await? else {
+            panic!("expected a room code");
         };
+        let (mut guest, _) =
+            connect_async(url::Url::parse(&format!("{server}/room/{code}"))?).await?;
+        for player in [&mut host, &mut guest] {
+            let message: ServerMessage = read_message(player).await?;
+            assert_eq!(message.action, ServerAction::SetBoard);
+        }
+
+        let (mut player, _) =
+            connect_async(url::Url::parse(&format!("{server}/bot/easy"))?).await?;
+        let message: ServerMessage = read_message(&mut player).await?;
+        assert_eq!(message.action, ServerAction::SetBoard);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_games() -> Result<()> {
+        let server = spawn_test_server(GameHistory::open_in_memory()?).await?;
+
+        let mut players = Vec::new();
+        for _ in 0..4 {
+            let player = join(&server, JoinRequest::QuickMatch).await?;
+            players.push(player);
+        }
+
+        for player in players.iter_mut() {
+            let message: ServerMessage = read_message(player).await?;
+            assert_eq!(message.action, ServerAction::SetBoard);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_private_room() -> Result<()> {
+        let server = spawn_test_server(GameHistory::open_in_memory()?).await?;
+
+        let (mut host, code) = create_room(&server, RoomSettings::default()).await?;
+
+        let mut stranger = join(&server, JoinRequest::JoinRoom("NOPE".to_string())).await?;
+        let response: JoinResponse = read_message(&mut stranger).await?;
+        assert_eq!(response, JoinResponse::RoomNotFound);
+
+        let mut guest = join(&server, JoinRequest::JoinRoom(code)).await?;
+
+        for player in [&mut host, &mut guest] {
+            let message: ServerMessage = read_message(player).await?;
+            assert_eq!(message.action, ServerAction::SetBoard);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resume_seat() -> Result<()> {
+        let server = spawn_test_server(GameHistory::open_in_memory()?).await?;
+
+        let (mut host, code) = create_room(&server, RoomSettings::default()).await?;
+        let mut guest = join(&server, JoinRequest::JoinRoom(code)).await?;
+
+        let set_board: ServerMessage = read_message(&mut host).await?;
+        let _: ServerMessage = read_message(&mut guest).await?;
+        host.close(None).await?;
+
+        let message: ServerMessage = read_message(&mut guest).await?;
+        assert_eq!(message.action, ServerAction::OpponentDisconnected);
+
+        let mut impostor = join(&server, JoinRequest::Resume("bogus".to_string())).await?;
+        let response: JoinResponse = read_message(&mut impostor).await?;
+        assert_eq!(response, JoinResponse::ResumeFailed);
+
+        let mut host = join(
+            &server,
+            JoinRequest::Resume(set_board.resume_token.unwrap()),
+        )
+        .await?;
+
+        let message: ServerMessage = read_message(&mut host).await?;
+        assert_eq!(message.action, ServerAction::Resumed);
+        assert_eq!(message.player_view, set_board.player_view);
+        let message: ServerMessage = read_message(&mut guest).await?;
+        assert_eq!(message.action, ServerAction::OpponentReconnected);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spectator() -> Result<()> {
+        let server = spawn_test_server(GameHistory::open_in_memory()?).await?;
+
+        let settings = RoomSettings {
+            reveal_hands_to_spectators: true,
+            ..RoomSettings::default()
+        };
+        let (mut host, code) = create_room(&server, settings).await?;
+        let mut guest = join(&server, JoinRequest::JoinRoom(code.clone())).await?;
+        let _: ServerMessage = read_message(&mut host).await?;
+        let _: ServerMessage = read_message(&mut guest).await?;
+
+        let mut spectator = join(&server, JoinRequest::Spectate(code)).await?;
+        let message: SpectatorMessage = read_message(&mut spectator).await?;
+        assert_eq!(message.action, SpectatorAction::SetBoard);
+
+        spectator
+            .send(Message::Text(serde_json::to_string(
+                &PlayerAction::DrawCard,
+            )?))
+            .await?;
+        let message: SpectatorMessage = read_message(&mut spectator).await?;
+        assert_eq!(message.action, SpectatorAction::ActionRejected);
+
+        host.send(Message::Text(serde_json::to_string(
+            &PlayerAction::DrawCard,
+        )?))
+        .await?;
+        let host_message: ServerMessage = read_message(&mut host).await?;
+        let message: SpectatorMessage = read_message(&mut spectator).await?;
+        assert_eq!(message.action, SpectatorAction::NormalMove);
         assert_eq!(
-            empty_board_message,
-            serde_json::from_str(&message1).unwrap()
+            message.spectator_view.player1_hand,
+            [true, false, false, false]
         );
         assert_eq!(
-            empty_board_message,
-            serde_json::from_str(&message2).unwrap()
+            message.spectator_view.player1_cards,
+            Some(host_message.player_view.player_hand)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_move_rejected() -> Result<()> {
+        let history = GameHistory::open_in_memory()?;
+        let server = spawn_test_server(history.clone()).await?;
+
+        let mut p1 = join(&server, JoinRequest::QuickMatch).await?;
+        let mut p2 = join(&server, JoinRequest::QuickMatch).await?;
+        let _: ServerMessage = read_message(&mut p1).await?;
+        let _: ServerMessage = read_message(&mut p2).await?;
+
+        let place_card = PlayerAction::PlaceCard(0, Side::LEFT);
+        p1.send(Message::Text(serde_json::to_string(&place_card)?))
+            .await?;
+        let message: ServerMessage = read_message(&mut p1).await?;
+        assert_eq!(
+            message.action,
+            ServerAction::MoveRejected(RejectReason::NoCardToPlace)
+        );
+
+        // The rejected move produced no update, so the next thing the opponent
+        // hears about is the draw.
+        p1.send(Message::Text(serde_json::to_string(
+            &PlayerAction::DrawCard,
+        )?))
+        .await?;
+        let message: ServerMessage = read_message(&mut p2).await?;
+        assert_eq!(message.action, ServerAction::NormalMove);
+        assert_eq!(
+            message.player_view.opponent_hand,
+            [true, false, false, false]
+        );
+
+        // Only the move that was applied makes it into the game's log.
+        let game = &history.list_games().await?[0];
+        let log = history.game_log(game.id).await?.unwrap();
+        assert_eq!(game.winner, None);
+        assert_eq!(log.actions.len(), 1);
+        assert_eq!(log.actions[0].player, Some(Player::PLAYER1));
+        assert_eq!(log.actions[0].action, PlayerAction::DrawCard);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_automatic_flip() -> Result<()> {
+        let server = spawn_test_server(GameHistory::open_in_memory()?).await?;
+
+        let settings = RoomSettings {
+            flip_mode: FlipMode::Automatic,
+            ..RoomSettings::default()
+        };
+        let (mut host, code) = create_room(&server, settings).await?;
+        let mut guest = join(&server, JoinRequest::JoinRoom(code)).await?;
+        let _: ServerMessage = read_message(&mut host).await?;
+
+        host.send(Message::Text(serde_json::to_string(&PlayerAction::Flip)?))
+            .await?;
+        let message: ServerMessage = read_message(&mut host).await?;
+        assert_eq!(
+            message.action,
+            ServerAction::MoveRejected(RejectReason::ManualFlipDisabled)
+        );
+
+        // With full hands and nothing on the active piles yet, nobody can move.
+        for _ in 0..4 {
+            for player in [&mut host, &mut guest] {
+                player
+                    .send(Message::Text(serde_json::to_string(
+                        &PlayerAction::DrawCard,
+                    )?))
+                    .await?;
+            }
+        }
+        loop {
+            let message: ServerMessage = read_message(&mut host).await?;
+            if message.action == ServerAction::StalemateCountdown(3) {
+                break;
+            }
+            assert_eq!(message.action, ServerAction::NormalMove);
+        }
+
+        let message: ServerMessage = read_message(&mut host).await?;
+        assert_eq!(message.action, ServerAction::NormalMove);
+        assert!(message.player_view.active_cards.iter().all(|x| x.is_some()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replay() -> Result<()> {
+        let history = GameHistory::open_in_memory()?;
+        let server = spawn_test_server(history.clone()).await?;
+
+        let mut p1 = join(&server, JoinRequest::QuickMatch).await?;
+        let mut p2 = join(&server, JoinRequest::QuickMatch).await?;
+        let _: ServerMessage = read_message(&mut p1).await?;
+        for player in [&mut p1, &mut p2] {
+            player
+                .send(Message::Text(serde_json::to_string(
+                    &PlayerAction::DrawCard,
+                )?))
+                .await?;
+        }
+        // Own draw and the opponent's, in whichever order the server applied them.
+        for _ in 0..2 {
+            let _: ServerMessage = read_message(&mut p1).await?;
+        }
+
+        let game_id = history.list_games().await?[0].id;
+        let mut viewer = join(&server, JoinRequest::Replay(game_id)).await?;
+        let message: ReplayMessage = read_message(&mut viewer).await?;
+        assert_eq!(message.move_index, 0);
+        assert_eq!(message.move_count, 2);
+
+        // The moves were only milliseconds apart, so playback reaches the end quickly.
+        loop {
+            let message: ReplayMessage = read_message(&mut viewer).await?;
+            if !message.playing {
+                assert_eq!(message.move_index, 2);
+                break;
+            }
+        }
+
+        viewer
+            .send(Message::Text(serde_json::to_string(&ReplayControl::Seek(
+                0,
+            ))?))
+            .await?;
+        let message: ReplayMessage = read_message(&mut viewer).await?;
+        let ReplayView::Omniscient(view) = message.view else {
+            panic!("expected the omniscient view");
+        };
+        assert_eq!(view.action, SpectatorAction::SetBoard);
+        assert_eq!(view.spectator_view.player1_hand, [false; 4]);
+
+        let perspective = ReplayPerspective::Player(Player::PLAYER2);
+        viewer
+            .send(Message::Text(serde_json::to_string(
+                &ReplayControl::Perspective(perspective),
+            )?))
+            .await?;
+        let message: ReplayMessage = read_message(&mut viewer).await?;
+        assert!(matches!(message.view, ReplayView::Player(_)));
+
+        let mut lost = join(&server, JoinRequest::Replay(game_id + 1)).await?;
+        let response: JoinResponse = read_message(&mut lost).await?;
+        assert_eq!(response, JoinResponse::ReplayNotFound);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bot_game() -> Result<()> {
+        let server = spawn_test_server(GameHistory::open_in_memory()?).await?;
+
+        let mut player = join(&server, JoinRequest::Bot(bot::BotLevel::Hard)).await?;
+        let message: ServerMessage = read_message(&mut player).await?;
+        assert_eq!(message.action, ServerAction::SetBoard);
+
+        // Left alone, the bot starts filling its hand within a reaction or two.
+        let message: ServerMessage = read_message(&mut player).await?;
+        assert_eq!(message.action, ServerAction::NormalMove);
+        assert!(message.player_view.opponent_hand.contains(&true));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_room_rules() -> Result<()> {
+        let server = spawn_test_server(GameHistory::open_in_memory()?).await?;
+
+        let impossible = RoomSettings {
+            rules: game_logic::RulesConfig {
+                player_pile_size: 30,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = join(&server, JoinRequest::CreateRoom(impossible)).await?;
+        let response: JoinResponse = read_message(&mut host).await?;
+        assert!(matches!(response, JoinResponse::InvalidRules(_)));
+
+        let settings = RoomSettings {
+            rules: game_logic::RulesConfig {
+                hand_size: 5,
+                wraparound: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (mut host, code) = create_room(&server, settings.clone()).await?;
+        let mut guest = join(&server, JoinRequest::JoinRoom(code)).await?;
+
+        for player in [&mut host, &mut guest] {
+            let message: ServerMessage = read_message(player).await?;
+            assert_eq!(message.player_view.player_hand, vec![None; 5]);
+            assert_eq!(message.player_view.opponent_hand, vec![false; 5]);
+            assert_eq!(message.player_view.rules, settings.rules);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_three_player_room() -> Result<()> {
+        let server = spawn_test_server(GameHistory::open_in_memory()?).await?;
+
+        let settings = RoomSettings {
+            rules: game_logic::RulesConfig::for_players(game_logic::Variant::House, 3),
+            ..Default::default()
+        };
+        let (host, code) = create_room(&server, settings).await?;
+
+        let mut players = vec![host];
+        for _ in 0..2 {
+            let guest = join(&server, JoinRequest::JoinRoom(code.clone())).await?;
+            players.push(guest);
+        }
+        // The first guest is told the room is still waiting for someone.
+        let response: JoinResponse = read_message(&mut players[1]).await?;
+        assert_eq!(
+            response,
+            JoinResponse::RoomJoined {
+                seated: 2,
+                players: 3
+            }
+        );
+
+        for player in &mut players {
+            let message: ServerMessage = read_message(player).await?;
+            assert_eq!(message.action, ServerAction::SetBoard);
+            assert_eq!(message.player_view.opponents.len(), 2);
+            assert_eq!(message.player_view.active_cards.len(), 3);
+        }
+
+        // Everyone hears about a draw, with the drawing player's hand filled in.
+        players[2]
+            .send(Message::Text(serde_json::to_string(
+                &PlayerAction::DrawCard,
+            )?))
+            .await?;
+        for (index, player) in players.iter_mut().enumerate() {
+            let message: ServerMessage = read_message(player).await?;
+            let third_seat = message
+                .player_view
+                .opponents
+                .iter()
+                .find(|seat| seat.player == Player::PLAYER3);
+            match third_seat {
+                Some(seat) => assert!(seat.hand[0], "seen from seat {index}"),
+                None => assert!(message.player_view.player_hand[0].is_some()),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_room_drops_players_who_leave() -> Result<()> {
+        let server = spawn_test_server(GameHistory::open_in_memory()?).await?;
+
+        let settings = RoomSettings {
+            rules: game_logic::RulesConfig::for_players(game_logic::Variant::House, 3),
+            ..Default::default()
+        };
+        let (mut host, code) = create_room(&server, settings).await?;
+        let waiting = JoinResponse::RoomJoined {
+            seated: 2,
+            players: 3,
+        };
+
+        let mut leaver = join(&server, JoinRequest::JoinRoom(code.clone())).await?;
+        assert_eq!(read_message::<JoinResponse>(&mut leaver).await?, waiting);
+        leaver.close(None).await?;
+        let left = JoinResponse::RoomLeft {
+            seated: 1,
+            players: 3,
+        };
+        assert_eq!(read_message::<JoinResponse>(&mut host).await?, left);
+
+        // The seat that was left is free again, so it takes two more to start.
+        let mut players = vec![host];
+        for _ in 0..2 {
+            let guest = join(&server, JoinRequest::JoinRoom(code.clone())).await?;
+            players.push(guest);
+        }
+        assert_eq!(
+            read_message::<JoinResponse>(&mut players[1]).await?,
+            waiting
         );
+        for player in &mut players {
+            let message: ServerMessage = read_message(player).await?;
+            assert_eq!(message.action, ServerAction::SetBoard);
+        }
 
         Ok(())
     }
@@ -1,12 +1,31 @@
 use serde::{Deserialize, Serialize};
 
-use crate::game_logic::Side;
+use crate::game_logic::{Player, Side, SpeedError, SpeedTable, TableEvent};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum PlayerAction {
     DrawCard,
     Flip,
     PlaceCard(usize, Side),
+    /// Give up the seat. With three or four players and no teams the others
+    /// play on; otherwise the game ends.
+    Forfeit,
+}
+
+impl PlayerAction {
+    /// Make this move on `player`'s behalf.
+    pub fn apply(
+        self,
+        table: &mut SpeedTable,
+        player: Player,
+    ) -> Result<Vec<TableEvent>, SpeedError> {
+        match self {
+            PlayerAction::DrawCard => table.player_draw_card(player),
+            PlayerAction::Flip => table.vote_flip(player),
+            PlayerAction::PlaceCard(hand_index, side) => table.place_card(player, side, hand_index),
+            PlayerAction::Forfeit => table.retire(player),
+        }
+    }
 }
 
 #[cfg(test)]
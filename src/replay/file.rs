@@ -0,0 +1,251 @@
+use std::io::{BufRead, Write};
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::ReplayTimeline;
+use crate::{
+    game_logic::Player,
+    game_session::GameEnd,
+    history::{GameLog, LoggedAction},
+    RoomSettings,
+};
+
+/// Bumped whenever a change to the format would stop older readers making
+/// sense of a file.
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+/// First line of a replay file: everything needed to deal the same table.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ReplayHeader {
+    pub version: u32,
+    pub seed: u64,
+    pub settings: RoomSettings,
+    /// Milliseconds since the Unix epoch.
+    pub started_at: i64,
+}
+
+/// How the game was recorded as ending.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ReplayOutcome {
+    pub winner: Player,
+    pub end: GameEnd,
+}
+
+/// Every line after the header.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ReplayEntry {
+    Action(LoggedAction),
+    /// Last line of a finished game.
+    Outcome(ReplayOutcome),
+}
+
+/// A game in the JSON lines replay format: the header, one line per applied
+/// action, and the outcome once the game has finished.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayFile {
+    pub header: ReplayHeader,
+    pub actions: Vec<LoggedAction>,
+    pub outcome: Option<ReplayOutcome>,
+}
+
+impl ReplayFile {
+    pub fn from_log(log: &GameLog) -> ReplayFile {
+        let outcome = log
+            .game
+            .winner
+            .zip(log.game.end)
+            .map(|(winner, end)| ReplayOutcome { winner, end });
+        ReplayFile {
+            header: ReplayHeader {
+                version: REPLAY_FORMAT_VERSION,
+                seed: log.game.seed,
+                settings: log.game.settings.clone(),
+                started_at: log.game.started_at,
+            },
+            actions: log.actions.clone(),
+            outcome,
+        }
+    }
+
+    pub fn read(reader: impl BufRead) -> Result<ReplayFile> {
+        let mut lines = reader.lines().enumerate();
+        let (_, header) = lines.next().context("replay file is empty")?;
+        let header: ReplayHeader =
+            serde_json::from_str(&header?).context("line 1 is not a replay header")?;
+        ensure!(
+            header.version == REPLAY_FORMAT_VERSION,
+            "replay format version {} is not supported, expected {REPLAY_FORMAT_VERSION}",
+            header.version
+        );
+
+        let mut actions = Vec::new();
+        let mut outcome = None;
+        for (index, line) in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if outcome.is_some() {
+                bail!("line {} comes after the outcome", index + 1);
+            }
+            let entry = serde_json::from_str(&line)
+                .with_context(|| format!("line {} is not a replay entry", index + 1))?;
+            match entry {
+                ReplayEntry::Action(action) => actions.push(action),
+                ReplayEntry::Outcome(recorded) => outcome = Some(recorded),
+            }
+        }
+
+        Ok(ReplayFile {
+            header,
+            actions,
+            outcome,
+        })
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> Result<()> {
+        writeln!(writer, "{}", serde_json::to_string(&self.header)?)?;
+        for action in &self.actions {
+            let entry = ReplayEntry::Action(action.clone());
+            writeln!(writer, "{}", serde_json::to_string(&entry)?)?;
+        }
+        if let Some(outcome) = self.outcome {
+            let entry = ReplayEntry::Outcome(outcome);
+            writeln!(writer, "{}", serde_json::to_string(&entry)?)?;
+        }
+        Ok(())
+    }
+
+    /// Re-run every action against a freshly dealt table and check that the game
+    /// ends the way the file says it did.
+    pub fn verify(&self) -> Result<ReplayTimeline> {
+        let timeline = ReplayTimeline::new(
+            self.header.seed,
+            self.header.settings.rules,
+            self.header.started_at,
+            &self.actions,
+        )?;
+        let winner = timeline.winner();
+
+        match (self.outcome, winner) {
+            (
+                Some(ReplayOutcome {
+                    winner: recorded,
+                    end: GameEnd::Won,
+                }),
+                Some(winner),
+            ) => ensure!(
+                recorded == winner,
+                "recorded winner is {recorded:?} but the moves make {winner:?} win"
+            ),
+            (
+                Some(ReplayOutcome {
+                    end: GameEnd::Won, ..
+                }),
+                None,
+            ) => {
+                bail!("recorded as won by playing out, but nobody runs out of cards")
+            }
+            (
+                Some(ReplayOutcome {
+                    end: GameEnd::Forfeit,
+                    ..
+                }),
+                Some(winner),
+            ) => {
+                bail!("recorded as a forfeit, but the moves make {winner:?} win outright")
+            }
+            (None, Some(winner)) => bail!("no outcome recorded, but the moves make {winner:?} win"),
+            (
+                Some(ReplayOutcome {
+                    end: GameEnd::Forfeit,
+                    ..
+                }),
+                None,
+            )
+            | (None, None) => {}
+        }
+
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game_logic::{test_support::play_out_with, Side, SpeedTable},
+        PlayerAction,
+    };
+
+    /// Play seed 0 out with the first legal move each turn until someone wins.
+    fn won_game() -> ReplayFile {
+        let mut actions = Vec::new();
+        let mut turn = 0;
+        let winner = play_out_with(&mut SpeedTable::from_seed(0), |_, player, action| {
+            if let Some(action) = action {
+                actions.push(LoggedAction {
+                    at: turn,
+                    player: Some(player),
+                    action,
+                });
+            }
+            turn += 1;
+        });
+        ReplayFile {
+            header: ReplayHeader {
+                version: REPLAY_FORMAT_VERSION,
+                seed: 0,
+                settings: RoomSettings::default(),
+                started_at: 0,
+            },
+            actions,
+            outcome: Some(ReplayOutcome {
+                winner,
+                end: GameEnd::Won,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_and_verify() {
+        let file = won_game();
+        let mut written = Vec::new();
+        file.write(&mut written).unwrap();
+
+        let read = ReplayFile::read(written.as_slice()).unwrap();
+        assert_eq!(read, file);
+        let timeline = read.verify().unwrap();
+        assert_eq!(timeline.winner(), Some(file.outcome.unwrap().winner));
+    }
+
+    #[test]
+    fn test_verify_catches_tampering() {
+        let mut wrong_winner = won_game();
+        let outcome = wrong_winner.outcome.as_mut().unwrap();
+        outcome.winner = outcome.winner.opponents(2).next().unwrap();
+        assert!(wrong_winner.verify().is_err());
+
+        let mut forfeit = won_game();
+        forfeit.outcome.as_mut().unwrap().end = GameEnd::Forfeit;
+        assert!(forfeit.verify().is_err());
+
+        let mut illegal = won_game();
+        illegal.actions.insert(
+            0,
+            LoggedAction {
+                at: 0,
+                player: Some(Player::PLAYER1),
+                action: PlayerAction::PlaceCard(0, Side::LEFT),
+            },
+        );
+        assert!(illegal.verify().is_err());
+
+        let mut newer = Vec::new();
+        let mut header = won_game().header;
+        header.version += 1;
+        writeln!(newer, "{}", serde_json::to_string(&header).unwrap()).unwrap();
+        assert!(ReplayFile::read(newer.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::{anyhow, bail, Result};
+use tokio::time::Duration;
+
+use crate::{
+    game_logic::{Player, RulesConfig, SpeedTable, TableEvent},
+    history::{GameLog, LoggedAction},
+    PlayerAction,
+};
+
+mod file;
+pub use file::{ReplayEntry, ReplayFile, ReplayHeader, ReplayOutcome, REPLAY_FORMAT_VERSION};
+
+mod playback;
+pub use playback::play_back;
+
+/// A stored game rebuilt move by move, keeping the table after every move so
+/// playback can jump anywhere without dealing again.
+pub struct ReplayTimeline {
+    /// `tables[i]` is the table after `i` moves; `tables[0]` is the deal.
+    tables: Vec<SpeedTable>,
+    steps: Vec<ReplayStep>,
+    started_at: i64,
+}
+
+/// One recorded move and what it did to the table.
+pub struct ReplayStep {
+    pub action: LoggedAction,
+    pub events: Vec<TableEvent>,
+}
+
+impl ReplayStep {
+    /// The player this move won the game for, if it did.
+    pub fn winner(&self) -> Option<Player> {
+        self.events.iter().find_map(|event| match event {
+            TableEvent::PlayerWon { player } => Some(*player),
+            _ => None,
+        })
+    }
+}
+
+impl ReplayTimeline {
+    pub fn from_log(log: &GameLog) -> Result<ReplayTimeline> {
+        ReplayTimeline::new(
+            log.game.seed,
+            log.game.settings.rules,
+            log.game.started_at,
+            &log.actions,
+        )
+    }
+
+    /// Deal from `seed` with `rules` and apply `actions` in order, failing on
+    /// the first one the table refuses.
+    pub fn new(
+        seed: u64,
+        rules: RulesConfig,
+        started_at: i64,
+        actions: &[LoggedAction],
+    ) -> Result<ReplayTimeline> {
+        let mut table = SpeedTable::with_rules(rules, seed)?;
+        let mut tables = vec![table.clone()];
+        let mut steps = Vec::with_capacity(actions.len());
+
+        for (index, logged) in actions.iter().enumerate() {
+            if steps
+                .last()
+                .is_some_and(|step: &ReplayStep| step.winner().is_some())
+            {
+                bail!("move {index} comes after the game was already won");
+            }
+            let result = match logged.player {
+                Some(player) if !table.players().any(|seated| seated == player) => {
+                    bail!("move {index} is by {player:?}, who isn't at the table")
+                }
+                Some(player) => logged.action.apply(&mut table, player),
+                None if logged.action == PlayerAction::Flip => table.flip_middle_cards(),
+                None => bail!("move {index} has no player but isn't a flip"),
+            };
+            let events = result.map_err(|error| {
+                anyhow!(
+                    "move {index} ({:?} by {:?}) is illegal: {error:?}",
+                    logged.action,
+                    logged.player
+                )
+            })?;
+            tables.push(table.clone());
+            steps.push(ReplayStep {
+                action: logged.clone(),
+                events,
+            });
+        }
+
+        Ok(ReplayTimeline {
+            tables,
+            steps,
+            started_at,
+        })
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn table_after(&self, moves: usize) -> &SpeedTable {
+        &self.tables[moves]
+    }
+
+    /// The move that takes the table from `table_after(index)` to `table_after(index + 1)`.
+    pub fn step(&self, index: usize) -> &ReplayStep {
+        &self.steps[index]
+    }
+
+    /// The winner, if the last move won the game.
+    pub fn winner(&self) -> Option<Player> {
+        self.steps.last()?.winner()
+    }
+
+    /// How long the game sat between the previous move, or the start, and move `index`.
+    pub fn delay_before(&self, index: usize) -> Duration {
+        let previous = match index {
+            0 => self.started_at,
+            _ => self.steps[index - 1].action.at,
+        };
+        let millis = self.steps[index].action.at.saturating_sub(previous);
+        Duration::from_millis(millis.max(0) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::{Side, Variant};
+
+    fn logged(at: i64, player: Option<Player>, action: PlayerAction) -> LoggedAction {
+        LoggedAction { at, player, action }
+    }
+
+    #[test]
+    fn test_timeline_rebuilds_table() {
+        let actions = [
+            logged(1_500, Some(Player::PLAYER1), PlayerAction::DrawCard),
+            logged(1_700, Some(Player::PLAYER2), PlayerAction::DrawCard),
+            logged(4_000, None, PlayerAction::Flip),
+        ];
+        let timeline = ReplayTimeline::new(5, RulesConfig::default(), 1_000, &actions).unwrap();
+
+        let mut table = SpeedTable::from_seed(5);
+        let _ = table.player_draw_card(Player::PLAYER1);
+        let _ = table.player_draw_card(Player::PLAYER2);
+        let _ = table.flip_middle_cards();
+
+        assert_eq!(timeline.move_count(), 3);
+        assert_eq!(
+            timeline.table_after(3).get_spectator_view(true),
+            table.get_spectator_view(true)
+        );
+        assert_eq!(
+            timeline.table_after(0).get_spectator_view(true),
+            SpeedTable::from_seed(5).get_spectator_view(true)
+        );
+        assert!(matches!(
+            timeline.step(2).events.as_slice(),
+            [TableEvent::MiddleFlipped { .. }]
+        ));
+        assert_eq!(timeline.delay_before(0), Duration::from_millis(500));
+        assert_eq!(timeline.delay_before(2), Duration::from_millis(2_300));
+        assert_eq!(timeline.winner(), None);
+    }
+
+    #[test]
+    fn test_timeline_rejects_illegal_move() {
+        let actions = [logged(
+            0,
+            Some(Player::PLAYER1),
+            PlayerAction::PlaceCard(0, Side::LEFT),
+        )];
+        assert!(ReplayTimeline::new(5, RulesConfig::default(), 0, &actions).is_err());
+    }
+
+    #[test]
+    fn test_timeline_rejects_missing_player() {
+        let actions = [logged(0, Some(Player::PLAYER3), PlayerAction::DrawCard)];
+        assert!(ReplayTimeline::new(5, RulesConfig::default(), 0, &actions).is_err());
+
+        let rules = RulesConfig::for_players(Variant::House, 3);
+        let timeline = ReplayTimeline::new(5, rules, 0, &actions).unwrap();
+        assert_eq!(timeline.move_count(), 1);
+    }
+}
@@ -0,0 +1,254 @@
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message;
+
+use super::ReplayTimeline;
+use crate::{
+    game_logic::TableEvent, game_session::wait_until, PlayerAction, PlayerConnection,
+    ReplayControl, ReplayMessage, ReplayPerspective, ReplayView, ServerAction, ServerMessage,
+    SpectatorAction, SpectatorMessage,
+};
+
+/// The slowest and fastest a viewer can play a replay, as multiples of the
+/// original timing.
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 16.0;
+
+/// Where a viewer is in the replay and how it is being played.
+struct Playback {
+    move_index: usize,
+    playing: bool,
+    speed: f64,
+    perspective: ReplayPerspective,
+    /// When the next move is due, while playing.
+    next_move_at: Option<Instant>,
+}
+
+/// Play `timeline` back to a viewer with the original timing between moves,
+/// until they disconnect. Playback starts right away from the deal.
+pub async fn play_back(mut connection: PlayerConnection, timeline: ReplayTimeline) -> Result<()> {
+    let mut playback = Playback {
+        move_index: 0,
+        playing: true,
+        speed: 1.0,
+        perspective: ReplayPerspective::default(),
+        next_move_at: None,
+    };
+    // Whether the last thing to happen was the next move rather than a jump.
+    let mut stepped = false;
+    let mut changed = true;
+
+    loop {
+        playback.schedule_next_move(&timeline);
+        if changed {
+            let message = playback.message(&timeline, stepped);
+            connection
+                .send(Message::Text(serde_json::to_string(&message)?))
+                .await?;
+        }
+        changed = true;
+
+        tokio::select! {
+            _ = wait_until(playback.next_move_at) => {
+                playback.move_index += 1;
+                playback.next_move_at = None;
+                stepped = true;
+            }
+            incoming = connection.next() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<ReplayControl>(&text) {
+                        Ok(control) => {
+                            playback.apply(control, &timeline);
+                            stepped = false;
+                        }
+                        Err(_) => changed = false,
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return Ok(()),
+                Some(Ok(_)) => changed = false,
+            },
+        }
+    }
+}
+
+impl Playback {
+    fn apply(&mut self, control: ReplayControl, timeline: &ReplayTimeline) {
+        match control {
+            ReplayControl::Play => self.playing = true,
+            ReplayControl::Pause => self.playing = false,
+            ReplayControl::Seek(move_index) => {
+                self.move_index = move_index.min(timeline.move_count());
+            }
+            ReplayControl::Speed(speed) if speed > 0.0 => {
+                self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+            }
+            ReplayControl::Speed(_) => {}
+            // Nobody sat in a seat past the table's player count.
+            ReplayControl::Perspective(ReplayPerspective::Player(player))
+                if !timeline
+                    .table_after(0)
+                    .players()
+                    .any(|seated| seated == player) => {}
+            ReplayControl::Perspective(perspective) => self.perspective = perspective,
+        }
+        // The wait for the next move starts over at the new position and speed.
+        self.next_move_at = None;
+    }
+
+    fn schedule_next_move(&mut self, timeline: &ReplayTimeline) {
+        if !self.playing || self.move_index >= timeline.move_count() {
+            self.playing = false;
+            self.next_move_at = None;
+        } else if self.next_move_at.is_none() {
+            let delay = timeline.delay_before(self.move_index).div_f64(self.speed);
+            self.next_move_at = Some(Instant::now() + delay);
+        }
+    }
+
+    /// The table at the current position, as the chosen perspective would have
+    /// seen it. After a jump the view is sent as a fresh `SetBoard`.
+    fn message(&self, timeline: &ReplayTimeline, stepped: bool) -> ReplayMessage {
+        let table = timeline.table_after(self.move_index);
+        let last_step =
+            (stepped && self.move_index > 0).then(|| timeline.step(self.move_index - 1));
+        let winner = last_step.and_then(|step| step.winner());
+
+        let view = match self.perspective {
+            ReplayPerspective::Player(player) => {
+                let action = match (last_step, winner) {
+                    (None, _) => ServerAction::SetBoard,
+                    (Some(_), Some(winner))
+                        if winner == player || table.rules().partner(winner) == Some(player) =>
+                    {
+                        ServerAction::GameWon
+                    }
+                    (Some(_), Some(_)) => ServerAction::GameLost,
+                    (Some(step), None) => {
+                        let flipped = step
+                            .events
+                            .iter()
+                            .any(|event| matches!(event, TableEvent::MiddleFlipped { .. }));
+                        let opponent_voted =
+                            step.action.player.is_some_and(|mover| mover != player)
+                                && step.action.action == PlayerAction::Flip;
+                        if opponent_voted && !flipped {
+                            ServerAction::FlipRequested
+                        } else {
+                            ServerAction::NormalMove
+                        }
+                    }
+                };
+                ReplayView::Player(ServerMessage {
+                    action,
+                    player_view: table.get_player_view(player),
+                    resume_token: None,
+                })
+            }
+            ReplayPerspective::Omniscient => {
+                let action = match (last_step, winner) {
+                    (None, _) => SpectatorAction::SetBoard,
+                    (Some(_), Some(winner)) => SpectatorAction::PlayerWon(winner),
+                    (Some(_), None) => SpectatorAction::NormalMove,
+                };
+                ReplayView::Omniscient(SpectatorMessage {
+                    action,
+                    spectator_view: table.get_spectator_view(true),
+                })
+            }
+        };
+
+        ReplayMessage {
+            move_index: self.move_index,
+            move_count: timeline.move_count(),
+            playing: self.playing,
+            speed: self.speed,
+            view,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game_logic::{
+            test_support::{play_out_with, table_with},
+            Player, RulesConfig, Variant,
+        },
+        history::LoggedAction,
+    };
+
+    #[test]
+    fn test_partners_win_together() {
+        let rules = RulesConfig {
+            teams: true,
+            ..RulesConfig::for_players(Variant::House, 4)
+        };
+        let mut actions = Vec::new();
+        let winner = play_out_with(&mut table_with(rules, 0), |_, player, action| {
+            if let Some(action) = action {
+                actions.push(LoggedAction {
+                    at: 0,
+                    player: Some(player),
+                    action,
+                });
+            }
+        });
+        let timeline = ReplayTimeline::new(0, rules, 0, &actions).unwrap();
+
+        for player in Player::ALL {
+            let playback = Playback {
+                move_index: timeline.move_count(),
+                playing: false,
+                speed: 1.0,
+                perspective: ReplayPerspective::Player(player),
+                next_move_at: None,
+            };
+            let ReplayView::Player(message) = playback.message(&timeline, true).view else {
+                panic!("expected {player:?}'s view");
+            };
+            let won = player == winner || player == winner.partner();
+            let expected = if won {
+                ServerAction::GameWon
+            } else {
+                ServerAction::GameLost
+            };
+            assert_eq!(message.action, expected, "seen by {player:?}");
+        }
+    }
+
+    #[test]
+    fn test_speed_is_clamped() {
+        let draw = LoggedAction {
+            at: 1_000,
+            player: Some(Player::PLAYER1),
+            action: PlayerAction::DrawCard,
+        };
+        let timeline = ReplayTimeline::new(0, RulesConfig::default(), 0, &[draw]).unwrap();
+        let mut playback = Playback {
+            move_index: 0,
+            playing: true,
+            speed: 1.0,
+            perspective: ReplayPerspective::default(),
+            next_move_at: None,
+        };
+
+        for (speed, expected) in [
+            (2.0, 2.0),
+            (1e-300, MIN_SPEED),
+            (1e300, MAX_SPEED),
+            (f64::INFINITY, MAX_SPEED),
+            (0.0, MAX_SPEED),
+            (-1.0, MAX_SPEED),
+            (f64::NAN, MAX_SPEED),
+        ] {
+            playback.apply(ReplayControl::Speed(speed), &timeline);
+            assert_eq!(playback.speed, expected, "after asking for {speed}");
+        }
+        // The slowest speed still schedules the next move instead of overflowing.
+        playback.apply(ReplayControl::Speed(1e-300), &timeline);
+        playback.schedule_next_move(&timeline);
+        assert!(playback.next_move_at.is_some());
+    }
+}
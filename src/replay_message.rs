@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{game_logic::Player, ServerMessage, SpectatorMessage};
+
+/// Sent by a replay viewer to steer playback.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ReplayControl {
+    Play,
+    Pause,
+    /// Jump to the table as it was after this many moves.
+    Seek(usize),
+    /// Playback speed relative to the original game, e.g. `2.0` for twice as fast.
+    Speed(f64),
+    Perspective(ReplayPerspective),
+}
+
+/// Whose eyes the replay is watched through.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum ReplayPerspective {
+    Player(Player),
+    /// Both hands face up.
+    #[default]
+    Omniscient,
+}
+
+/// The replayed table at one point in the game, sent whenever it or the
+/// playback state changes.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ReplayMessage {
+    /// Moves applied so far, from 0 for the deal up to `move_count`.
+    pub move_index: usize,
+    pub move_count: usize,
+    pub playing: bool,
+    pub speed: f64,
+    pub view: ReplayView,
+}
+
+/// What the chosen perspective would have been sent during the game.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ReplayView {
+    Player(ServerMessage),
+    Omniscient(SpectatorMessage),
+}
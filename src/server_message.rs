@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::game_logic::{Player, PlayerView};
+use crate::game_logic::{PlayerView, SpeedError};
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub enum ServerAction {
@@ -8,10 +8,60 @@ pub enum ServerAction {
     NormalMove,
     GameWon,
     GameLost,
+    Resumed,
+    OpponentDisconnected,
+    OpponentReconnected,
+    GameOver(GameSummary),
+    /// Only sent to the player whose move was refused; the table is unchanged.
+    MoveRejected(RejectReason),
+    /// The opponent is stuck and wants the middle cards flipped.
+    FlipRequested,
+    /// Neither player can move; the server flips the middle cards after this many seconds.
+    StalemateCountdown(u32),
 }
 
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// Why a move was refused: one code per `SpeedError` a move can fail with, plus
+/// moves the room's settings don't allow.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum RejectReason {
+    NoCardToDraw,
+    HandAlreadyFull,
+    NoCardToPlace,
+    NoCardToPlaceOn,
+    NotAdjacentCard,
+    FlipAlreadyRequested,
+    ManualFlipDisabled,
+}
+
+impl RejectReason {
+    pub fn for_error(error: &SpeedError) -> RejectReason {
+        match error {
+            SpeedError::NoCardToDraw => RejectReason::NoCardToDraw,
+            SpeedError::HandAlreadyFull => RejectReason::HandAlreadyFull,
+            SpeedError::NoCardToPlace => RejectReason::NoCardToPlace,
+            SpeedError::NoCardToPlaceOn => RejectReason::NoCardToPlaceOn,
+            SpeedError::NotAdjacentCard => RejectReason::NotAdjacentCard,
+            SpeedError::FlipAlreadyRequested => RejectReason::FlipAlreadyRequested,
+        }
+    }
+}
+
+/// Final numbers for a finished game, from the point of view of the receiving player.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GameSummary {
+    pub won: bool,
+    pub duration_ms: u64,
+    pub cards_played: u32,
+    /// Cards played by everyone else, added together.
+    pub opponent_cards_played: u32,
+    pub middle_flips: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct ServerMessage {
     pub action: ServerAction,
     pub player_view: PlayerView,
+    /// Only sent with `SetBoard`; reconnecting with it reclaims the same seat.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_token: Option<String>,
 }
@@ -0,0 +1,166 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use tokio::time::Duration;
+
+use crate::{
+    bot::{Decision, Strategy, StrategyContext},
+    game_logic::{Player, SpeedTable, TableEvent},
+    RejectReason,
+};
+
+/// Games still going after this many applied actions are called off unfinished.
+pub const MAX_ACTIONS: u32 = 10_000;
+/// Also called off after this many decisions, in case the strategies stop
+/// getting anywhere.
+const MAX_DECISIONS: u32 = 100_000;
+
+/// How one simulated game went.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameReport {
+    /// `None` if the game was called off first.
+    pub winner: Option<Player>,
+    /// Actions the table accepted.
+    pub actions: u32,
+    /// Actions the table refused, i.e. fumbles.
+    pub rejected: u32,
+    pub middle_flips: u32,
+    pub reshuffles: u32,
+    /// Times the table got into a state where neither player could move.
+    pub stalemates: u32,
+    /// Simulated time from the deal to the last action.
+    pub duration: Duration,
+}
+
+/// Play one game between two strategies on a simulated clock, driving them the
+/// way the server drives a seated bot: both are asked again whenever the table
+/// changes, and a refused action is only reported back to the strategy that
+/// made it. A table dealt from the same seed always plays out the same game.
+/// The table must be dealt for two players.
+pub fn simulate_game(mut table: SpeedTable, strategies: [&mut dyn Strategy; 2]) -> GameReport {
+    assert_eq!(table.rules().players, 2, "simulated games are one on one");
+    let mut rng = ChaCha8Rng::seed_from_u64(table.seed());
+    let players = [Player::PLAYER1, Player::PLAYER2];
+
+    let mut report = GameReport {
+        winner: None,
+        actions: 0,
+        rejected: 0,
+        middle_flips: 0,
+        reshuffles: 0,
+        stalemates: 0,
+        duration: Duration::ZERO,
+    };
+    // When each strategy is next asked for a decision, in game time.
+    let mut next_decision_at = [Some(Duration::ZERO); 2];
+    let mut last_rejection = [None; 2];
+    let mut last_change = Duration::ZERO;
+    let mut decisions = 0;
+
+    while report.actions < MAX_ACTIONS && decisions < MAX_DECISIONS {
+        // Ties go to the first seat so the order never depends on anything else.
+        let Some(seat) = (0..2)
+            .filter(|&seat| next_decision_at[seat].is_some())
+            .min_by_key(|&seat| next_decision_at[seat])
+        else {
+            break;
+        };
+        let player = players[seat];
+        let now = next_decision_at[seat].take().unwrap();
+        decisions += 1;
+
+        let context = StrategyContext {
+            elapsed: now,
+            since_last_change: now - last_change,
+            can_vote_flip: true,
+            last_rejection: last_rejection[seat],
+        };
+        let view = table.get_player_view(player);
+        let action = match strategies[seat].decide(&view, &context, &mut rng) {
+            Decision::Act(action) => action,
+            Decision::Wait(delay) => {
+                next_decision_at[seat] = now.checked_add(delay);
+                continue;
+            }
+            Decision::WaitForChange => continue,
+        };
+        let was_stalemate = table.is_stalemate();
+
+        let events = match action.apply(&mut table, player) {
+            Ok(events) => events,
+            Err(error) => {
+                report.rejected += 1;
+                last_rejection[seat] = Some(RejectReason::for_error(&error));
+                next_decision_at[seat] = Some(now);
+                continue;
+            }
+        };
+        report.actions += 1;
+        report.duration = now;
+        for event in &events {
+            match event {
+                TableEvent::MiddleFlipped { .. } => report.middle_flips += 1,
+                TableEvent::MiddleReshuffled { .. } => report.reshuffles += 1,
+                TableEvent::PlayerWon { player } => report.winner = Some(*player),
+                _ => {}
+            }
+        }
+        if report.winner.is_some() {
+            break;
+        }
+        if !was_stalemate && table.is_stalemate() {
+            report.stalemates += 1;
+        }
+
+        last_change = now;
+        last_rejection = [None; 2];
+        next_decision_at = [Some(now); 2];
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::{Blocking, BotConfig, BotLevel, Greedy, HumanLike, Random};
+
+    #[test]
+    fn test_simulation_is_deterministic() {
+        for seed in 0..10 {
+            let play = || {
+                let mut easy = BotLevel::Easy.strategy();
+                let mut hard = BotLevel::Hard.strategy();
+                simulate_game(SpeedTable::from_seed(seed), [easy.as_mut(), hard.as_mut()])
+            };
+            let report = play();
+            assert_eq!(report, play());
+            assert!(report.winner.is_some(), "seed {seed} didn't finish");
+        }
+    }
+
+    #[test]
+    fn test_faster_bot_wins_more() {
+        let careful = |level: BotLevel| BotConfig {
+            error_rate: 0.0,
+            ..level.config()
+        };
+        let fast_wins = (0..50)
+            .filter(|&seed| {
+                let mut slow = HumanLike::new(careful(BotLevel::Easy), Greedy);
+                let mut fast = HumanLike::new(careful(BotLevel::Hard), Greedy);
+                simulate_game(SpeedTable::from_seed(seed), [&mut slow, &mut fast]).winner
+                    == Some(Player::PLAYER2)
+            })
+            .count();
+        assert!(fast_wins > 25, "fast bot only won {fast_wins} of 50");
+    }
+
+    #[test]
+    fn test_reference_strategies_finish() {
+        for seed in 0..20 {
+            let report = simulate_game(SpeedTable::from_seed(seed), [&mut Blocking, &mut Random]);
+            assert!(report.winner.is_some(), "seed {seed} didn't finish");
+            assert_eq!(report.rejected, 0);
+        }
+    }
+}
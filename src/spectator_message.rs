@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game_logic::{Player, SpectatorView};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum SpectatorAction {
+    SetBoard,
+    NormalMove,
+    PlayerWon(Player),
+    StalemateCountdown(u32),
+    /// Sent back when a spectator tries to make a move; spectators can only watch.
+    ActionRejected,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SpectatorMessage {
+    pub action: SpectatorAction,
+    pub spectator_view: SpectatorView,
+}